@@ -0,0 +1,34 @@
+extern crate device_query;
+
+use device_query::{DeviceControl, DeviceQuery, DeviceState, Keycode, MouseButton, ScrollDelta};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let device_state = DeviceState::new();
+
+    device_state.mouse_move_to((100, 100)).unwrap();
+    println!("Mouse moved to {:?}", device_state.get_mouse().coords);
+
+    device_state.key_down(Keycode::LShift).unwrap();
+    device_state.key_down(Keycode::A).unwrap();
+    println!("Keys pressed: {:?}", device_state.get_keys());
+    device_state.key_up(Keycode::A).unwrap();
+    device_state.key_up(Keycode::LShift).unwrap();
+
+    device_state.mouse_button(MouseButton::Left, true).unwrap();
+    println!(
+        "Buttons pressed: {:?}",
+        device_state.get_mouse().pressed_buttons()
+    );
+    device_state.mouse_button(MouseButton::Left, false).unwrap();
+
+    device_state
+        .scroll(ScrollDelta {
+            vertical: -3,
+            horizontal: 0,
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    println!("Scrolled by {:?}", device_state.get_mouse().scroll_delta);
+}
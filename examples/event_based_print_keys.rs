@@ -13,6 +13,9 @@ fn main() {
     let _guard = event_handler.on_key_up(|key| {
         println!("Up: {:#?}", key);
     });
+    let _guard = event_handler.on_char(|character| {
+        println!("Typed: {:?}", character);
+    });
 
     loop {
         thread::sleep(Duration::from_secs(1000));
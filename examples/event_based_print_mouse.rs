@@ -10,6 +10,9 @@ fn main() {
     let _guard = event_handler.on_mouse_move(|position| {
         println!("Position: {:#?}", position);
     });
+    let _guard = event_handler.on_mouse_motion(|delta| {
+        println!("Motion: {:?}", delta);
+    });
     let _guard = event_handler.on_mouse_down(|button| {
         println!("Down: {:#?}", button);
     });
@@ -0,0 +1,23 @@
+extern crate device_query;
+
+use device_query::{DeviceEvent, DeviceEventsHandler};
+use std::time::Duration;
+
+fn main() {
+    let event_handler = DeviceEventsHandler::new(Duration::from_millis(10))
+        .expect("Could not initialize event loop");
+
+    for event in event_handler.subscribe() {
+        match event {
+            DeviceEvent::KeyDown(event) => println!("Key down: {:?}", event),
+            DeviceEvent::KeyUp(event) => println!("Key up: {:?}", event),
+            DeviceEvent::Char(character) => println!("Typed: {:?}", character),
+            DeviceEvent::MouseMove(position) => println!("Position: {:?}", position),
+            DeviceEvent::MouseMotion(delta) => println!("Motion: {:?}", delta),
+            DeviceEvent::MouseDown(button) => println!("Mouse down: {:?}", button),
+            DeviceEvent::MouseUp(button) => println!("Mouse up: {:?}", button),
+            DeviceEvent::Scroll(event) => println!("Scroll: {:?}", event),
+            DeviceEvent::HiResScroll(scroll) => println!("High-resolution scroll: {:?}", scroll),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+extern crate device_query;
+
+use device_query::{DeviceEvents, DeviceEventsHandler, Hotkey};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let event_handler = DeviceEventsHandler::new(Duration::from_millis(10))
+        .expect("Could not initialize event loop");
+    let hotkey: Hotkey = "Ctrl+Shift+F9".parse().expect("Invalid hotkey");
+    let _guard = event_handler.on_hotkey(hotkey, |hotkey| {
+        println!("{} pressed", hotkey);
+    });
+
+    loop {
+        thread::sleep(Duration::from_secs(1000));
+    }
+}
@@ -1,6 +1,6 @@
 extern crate device_query;
 
-use device_query::{DeviceEvents, DeviceEventsHandler, MouseScrollEvent};
+use device_query::{DeviceEvents, DeviceEventsHandler, HiResScroll, MouseScrollEvent};
 use std::thread;
 use std::time::Duration;
 
@@ -19,6 +19,14 @@ fn main() {
             MouseScrollEvent::HorizontalLeft => println!("Scroll Left"),
         }
     });
+    let _guard = event_handler.on_hi_res_scroll(|scroll| {
+        println!(
+            "  {:+.2} notches vertically, {:+.2} horizontally ({:?})",
+            scroll.vertical as f64 / HiResScroll::NOTCH as f64,
+            scroll.horizontal as f64 / HiResScroll::NOTCH as f64,
+            scroll.source
+        );
+    });
 
     loop {
         thread::sleep(Duration::from_secs(1));
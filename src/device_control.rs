@@ -0,0 +1,88 @@
+//! Control functions.
+
+use DeviceState;
+#[cfg(target_os = "linux")]
+use VirtualDevice;
+use {Error, Keycode, MouseButton, MousePosition, ScrollDelta};
+
+/// Trait to synthesize input events, as if they came from the devices.
+///
+/// On Linux, [`DeviceState`] uses the XTest extension of the X server, and
+/// [`VirtualDevice`] a uinput virtual device which also works on
+/// Wayland and in a console. It isn't supported on Windows and macOS yet, where every
+/// function returns [`Error::BackendUnavailable`].
+///
+/// ```no_run
+/// use device_query::{DeviceControl, DeviceQuery, DeviceState, Keycode};
+///
+/// let device_state = DeviceState::new();
+/// device_state.key_down(Keycode::LShift).unwrap();
+/// assert!(device_state.get_keys().contains(&Keycode::LShift));
+/// device_state.key_up(Keycode::LShift).unwrap();
+///
+/// device_state.mouse_move_to((100, 200)).unwrap();
+/// assert_eq!(device_state.get_mouse().coords, (100, 200));
+/// ```
+pub trait DeviceControl {
+    /// Press a key.
+    fn key_down(&self, key: Keycode) -> Result<(), Error>;
+
+    /// Release a key.
+    fn key_up(&self, key: Keycode) -> Result<(), Error>;
+
+    /// Move the mouse to a position, in pixels of the screen.
+    fn mouse_move_to(&self, position: MousePosition) -> Result<(), Error>;
+
+    /// Press or release a mouse button.
+    fn mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error>;
+
+    /// Scroll the mouse wheel by a number of notches in each direction.
+    fn scroll(&self, delta: ScrollDelta) -> Result<(), Error>;
+}
+
+impl DeviceControl for DeviceState {
+    fn key_down(&self, key: Keycode) -> Result<(), Error> {
+        self.send_key(key, true)
+    }
+
+    fn key_up(&self, key: Keycode) -> Result<(), Error> {
+        self.send_key(key, false)
+    }
+
+    fn mouse_move_to(&self, position: MousePosition) -> Result<(), Error> {
+        self.send_mouse_move(position)
+    }
+
+    fn mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        self.send_mouse_button(button, pressed)
+    }
+
+    fn scroll(&self, delta: ScrollDelta) -> Result<(), Error> {
+        self.send_scroll(delta)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DeviceControl for VirtualDevice {
+    fn key_down(&self, key: Keycode) -> Result<(), Error> {
+        self.send_key(key, true)
+    }
+
+    fn key_up(&self, key: Keycode) -> Result<(), Error> {
+        self.send_key(key, false)
+    }
+
+    /// Fails with [`Error::UnsupportedInput`] unless the device was created
+    /// [`with_screen_size`](VirtualDevice::with_screen_size).
+    fn mouse_move_to(&self, position: MousePosition) -> Result<(), Error> {
+        self.send_mouse_move(position)
+    }
+
+    fn mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        self.send_mouse_button(button, pressed)
+    }
+
+    fn scroll(&self, delta: ScrollDelta) -> Result<(), Error> {
+        self.send_scroll(delta)
+    }
+}
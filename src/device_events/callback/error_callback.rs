@@ -0,0 +1,37 @@
+//! Error callback.
+
+use crate::device_events::utils;
+use std::ops::DerefMut;
+use std::sync::{Arc, Mutex, Weak};
+use Error;
+
+/// Error callback.
+pub type ErrorCallback = dyn Fn(&Error) + Sync + Send + 'static;
+
+/// Error callbacks.
+#[derive(Default)]
+pub(crate) struct ErrorCallbacks {
+    error: Mutex<Vec<Weak<ErrorCallback>>>,
+}
+
+impl ErrorCallbacks {
+    pub fn push_error(&self, callback: Arc<ErrorCallback>) {
+        if let Ok(mut callbacks) = self.error.lock() {
+            let callback = Arc::downgrade(&callback);
+            callbacks.push(callback)
+        }
+    }
+
+    pub fn run_error(&self, error: &Error) {
+        if let Ok(mut callbacks) = self.error.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(error);
+                }
+            }
+        }
+    }
+}
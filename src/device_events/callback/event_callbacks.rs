@@ -0,0 +1,84 @@
+//! Callbacks and subscribers of an event loop.
+
+use super::{
+    ErrorCallbacks, HotkeyCallbacks, KeyboardCallbacks, MouseCallbacks, SequenceCallbacks,
+    TimedEventCallbacks,
+};
+use crate::device_events::{DeviceEvent, TimedEvent};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Receives every event dispatched by an event loop.
+pub(crate) trait EventSubscriber: Send {
+    /// Delivers the event. Returns false once the subscriber is gone and should be removed.
+    fn send(&self, event: &TimedEvent) -> bool;
+}
+
+impl EventSubscriber for Sender<DeviceEvent> {
+    fn send(&self, event: &TimedEvent) -> bool {
+        Sender::send(self, event.event.clone()).is_ok()
+    }
+}
+
+impl EventSubscriber for Sender<TimedEvent> {
+    fn send(&self, event: &TimedEvent) -> bool {
+        Sender::send(self, event.clone()).is_ok()
+    }
+}
+
+/// Every callback and subscriber registered on an event loop.
+#[derive(Default)]
+pub(crate) struct EventCallbacks {
+    pub keyboard: KeyboardCallbacks,
+    pub hotkeys: HotkeyCallbacks,
+    pub sequences: SequenceCallbacks,
+    pub mouse: MouseCallbacks,
+    pub error: ErrorCallbacks,
+    pub events: TimedEventCallbacks,
+    subscribers: Mutex<Vec<Box<dyn EventSubscriber>>>,
+}
+
+impl EventCallbacks {
+    pub fn push_subscriber(&self, subscriber: Box<dyn EventSubscriber>) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(subscriber)
+        }
+    }
+
+    /// Removes every subscriber, ending their subscription.
+    pub fn clear_subscribers(&self) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.clear()
+        }
+    }
+
+    /// Runs the callbacks registered for this event, which happened at `time`, and sends
+    /// it to every subscriber.
+    pub fn dispatch(&self, event: DeviceEvent, time: Instant) {
+        match event {
+            DeviceEvent::KeyDown(ref event) => {
+                self.keyboard.run_key_down(event);
+                self.hotkeys.run_key_down(event);
+                self.sequences.run_key_down(event, time);
+            }
+            DeviceEvent::KeyUp(ref event) => {
+                self.keyboard.run_key_up(event);
+                self.hotkeys.run_key_up(event);
+            }
+            DeviceEvent::Char(ref character) => self.keyboard.run_char(character),
+            DeviceEvent::MouseMove(ref position) => self.mouse.run_mouse_move(position),
+            DeviceEvent::MouseMotion(ref delta) => self.mouse.run_mouse_motion(delta),
+            DeviceEvent::MouseDown(ref button) => self.mouse.run_mouse_down(button),
+            DeviceEvent::MouseUp(ref button) => self.mouse.run_mouse_up(button),
+            DeviceEvent::Scroll(ref event) => self.mouse.run_mouse_scroll(event),
+            DeviceEvent::HiResScroll(ref scroll) => self.mouse.run_hi_res_scroll(scroll),
+        }
+        let event = TimedEvent { time, event };
+        self.events.run_event(&event);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            // Subscribers whose receiver was dropped are removed.
+            subscribers.retain(|subscriber| subscriber.send(&event));
+        }
+    }
+}
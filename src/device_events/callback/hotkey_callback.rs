@@ -0,0 +1,66 @@
+//! Hotkey callback.
+
+use crate::device_events::utils;
+use std::ops::DerefMut;
+use std::sync::{Arc, Mutex, Weak};
+use {Hotkey, KeyEvent};
+
+/// Hotkey callback.
+pub type HotkeyCallback = dyn Fn(&Hotkey) + Sync + Send + 'static;
+
+/// A registered hotkey.
+struct HotkeyEntry {
+    hotkey: Hotkey,
+    /// Whether the hotkey fired and its key wasn't released yet.
+    active: bool,
+    callback: Weak<HotkeyCallback>,
+}
+
+/// Hotkey callbacks.
+#[derive(Default)]
+pub(crate) struct HotkeyCallbacks {
+    hotkeys: Mutex<Vec<HotkeyEntry>>,
+}
+
+impl HotkeyCallbacks {
+    pub fn push_hotkey(&self, hotkey: Hotkey, callback: Arc<HotkeyCallback>) {
+        if let Ok(mut hotkeys) = self.hotkeys.lock() {
+            let callback = Arc::downgrade(&callback);
+            hotkeys.push(HotkeyEntry {
+                hotkey,
+                active: false,
+                callback,
+            })
+        }
+    }
+
+    /// Runs the callbacks of the hotkeys activated by this key press. A hotkey fires
+    /// once per activation, until its key is released.
+    pub fn run_key_down(&self, event: &KeyEvent) {
+        if let Ok(mut hotkeys) = self.hotkeys.lock() {
+            utils::DrainFilter::drain_filter(hotkeys.deref_mut(), |entry| {
+                entry.callback.upgrade().is_none()
+            });
+            for entry in hotkeys.iter_mut() {
+                if entry.active || !entry.hotkey.matches(event) {
+                    continue;
+                }
+                entry.active = true;
+                if let Some(callback) = entry.callback.upgrade() {
+                    callback(&entry.hotkey);
+                }
+            }
+        }
+    }
+
+    /// Ends the activation of the hotkeys of the released key.
+    pub fn run_key_up(&self, event: &KeyEvent) {
+        if let Ok(mut hotkeys) = self.hotkeys.lock() {
+            for entry in hotkeys.iter_mut() {
+                if entry.hotkey.key == event.key {
+                    entry.active = false;
+                }
+            }
+        }
+    }
+}
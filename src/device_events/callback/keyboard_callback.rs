@@ -1,16 +1,26 @@
 use crate::device_events::utils;
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex, Weak};
+use KeyEvent;
 use Keycode;
 
 /// Keyboard callback.
 pub type KeyboardCallback = dyn Fn(&Keycode) + Sync + Send + 'static;
 
+/// Keyboard callback getting the modifiers along with the key.
+pub type KeyEventCallback = dyn Fn(&KeyEvent) + Sync + Send + 'static;
+
+/// Typed character callback.
+pub type CharCallback = dyn Fn(&char) + Sync + Send + 'static;
+
 /// Keyboard callbacks.
 #[derive(Default)]
 pub(crate) struct KeyboardCallbacks {
     key_down: Mutex<Vec<Weak<KeyboardCallback>>>,
     key_up: Mutex<Vec<Weak<KeyboardCallback>>>,
+    key_down_event: Mutex<Vec<Weak<KeyEventCallback>>>,
+    key_up_event: Mutex<Vec<Weak<KeyEventCallback>>>,
+    chars: Mutex<Vec<Weak<CharCallback>>>,
 }
 
 impl KeyboardCallbacks {
@@ -28,27 +38,81 @@ impl KeyboardCallbacks {
         }
     }
 
-    pub fn run_key_up(&self, key: &Keycode) {
+    pub fn push_key_up_event(&self, callback: Arc<KeyEventCallback>) {
+        if let Ok(mut key_up_event) = self.key_up_event.lock() {
+            let callback = Arc::downgrade(&callback);
+            key_up_event.push(callback)
+        }
+    }
+
+    pub fn push_key_down_event(&self, callback: Arc<KeyEventCallback>) {
+        if let Ok(mut key_down_event) = self.key_down_event.lock() {
+            let callback = Arc::downgrade(&callback);
+            key_down_event.push(callback)
+        }
+    }
+
+    pub fn push_char(&self, callback: Arc<CharCallback>) {
+        if let Ok(mut chars) = self.chars.lock() {
+            let callback = Arc::downgrade(&callback);
+            chars.push(callback)
+        }
+    }
+
+    pub fn run_key_up(&self, event: &KeyEvent) {
         if let Ok(mut callbacks) = self.key_up.lock() {
             utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
                 callback.upgrade().is_none()
             });
             for callback in callbacks.iter() {
                 if let Some(callback) = callback.upgrade() {
-                    callback(key);
+                    callback(&event.key);
+                }
+            }
+        }
+        if let Ok(mut callbacks) = self.key_up_event.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(event);
                 }
             }
         }
     }
 
-    pub fn run_key_down(&self, key: &Keycode) {
+    pub fn run_key_down(&self, event: &KeyEvent) {
         if let Ok(mut callbacks) = self.key_down.lock() {
             utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
                 callback.upgrade().is_none()
             });
             for callback in callbacks.iter() {
                 if let Some(callback) = callback.upgrade() {
-                    callback(key);
+                    callback(&event.key);
+                }
+            }
+        }
+        if let Ok(mut callbacks) = self.key_down_event.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(event);
+                }
+            }
+        }
+    }
+
+    pub fn run_char(&self, character: &char) {
+        if let Ok(mut callbacks) = self.chars.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(character);
                 }
             }
         }
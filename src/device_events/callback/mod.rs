@@ -1,7 +1,17 @@
 mod callback_guard;
+mod error_callback;
+mod event_callbacks;
+mod hotkey_callback;
 mod keyboard_callback;
 mod mouse_callback;
+mod sequence_callback;
+mod timed_event_callback;
 
 pub use self::callback_guard::*;
+pub use self::error_callback::*;
+pub(crate) use self::event_callbacks::*;
+pub use self::hotkey_callback::*;
 pub use self::keyboard_callback::*;
 pub use self::mouse_callback::*;
+pub use self::sequence_callback::*;
+pub use self::timed_event_callback::*;
@@ -1,28 +1,37 @@
 //! Mouse callback.
 
 use crate::device_events::utils;
-use crate::mouse_state::MouseScrollEvent;
+use crate::mouse_state::{HiResScroll, MouseScrollEvent};
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex, Weak};
 use MouseButton;
+use MouseDelta;
 use MousePosition;
 
 /// Mouse move callback.
 pub type MouseMoveCallback = dyn Fn(&MousePosition) + Sync + Send + 'static;
 
+/// Mouse motion callback.
+pub type MouseMotionCallback = dyn Fn(&MouseDelta) + Sync + Send + 'static;
+
 /// Mouse button callback.
 pub type MouseButtonCallback = dyn Fn(&MouseButton) + Sync + Send + 'static;
 
 /// Mouse scroll callback.
 pub type MouseScrollCallback = dyn Fn(&MouseScrollEvent) + Sync + Send + 'static;
 
+/// High-resolution scroll callback.
+pub type HiResScrollCallback = dyn Fn(&HiResScroll) + Sync + Send + 'static;
+
 /// Mouse callbacks.
 #[derive(Default)]
 pub(crate) struct MouseCallbacks {
     pub mouse_move: Mutex<Vec<Weak<MouseMoveCallback>>>,
+    pub mouse_motion: Mutex<Vec<Weak<MouseMotionCallback>>>,
     pub mouse_up: Mutex<Vec<Weak<MouseButtonCallback>>>,
     pub mouse_down: Mutex<Vec<Weak<MouseButtonCallback>>>,
     pub mouse_scroll: Mutex<Vec<Weak<MouseScrollCallback>>>,
+    pub hi_res_scroll: Mutex<Vec<Weak<HiResScrollCallback>>>,
 }
 
 impl MouseCallbacks {
@@ -33,6 +42,13 @@ impl MouseCallbacks {
         }
     }
 
+    pub fn push_mouse_motion(&self, callback: Arc<MouseMotionCallback>) {
+        if let Ok(mut callbacks) = self.mouse_motion.lock() {
+            let callback = Arc::downgrade(&callback);
+            callbacks.push(callback)
+        }
+    }
+
     pub fn push_mouse_down(&self, callback: Arc<MouseButtonCallback>) {
         if let Ok(mut callbacks) = self.mouse_down.lock() {
             let callback = Arc::downgrade(&callback);
@@ -60,6 +76,19 @@ impl MouseCallbacks {
         }
     }
 
+    pub fn run_mouse_motion(&self, delta: &MouseDelta) {
+        if let Ok(mut callbacks) = self.mouse_motion.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(delta);
+                }
+            }
+        }
+    }
+
     pub fn run_mouse_down(&self, button: &MouseButton) {
         if let Ok(mut callbacks) = self.mouse_down.lock() {
             utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
@@ -105,4 +134,24 @@ impl MouseCallbacks {
             }
         }
     }
+
+    pub fn push_hi_res_scroll(&self, callback: Arc<HiResScrollCallback>) {
+        if let Ok(mut callbacks) = self.hi_res_scroll.lock() {
+            let callback = Arc::downgrade(&callback);
+            callbacks.push(callback)
+        }
+    }
+
+    pub fn run_hi_res_scroll(&self, scroll: &HiResScroll) {
+        if let Ok(mut callbacks) = self.hi_res_scroll.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(scroll);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,46 @@
+//! Key sequence callback.
+
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Instant;
+use {KeyEvent, KeySequence, SequenceMatch, SequenceMatcher};
+
+/// Key sequence callback.
+pub type SequenceCallback = dyn Fn(&KeySequence) + Sync + Send + 'static;
+
+/// Key sequence callbacks.
+#[derive(Default)]
+pub(crate) struct SequenceCallbacks {
+    matcher: Mutex<SequenceMatcher<(KeySequence, Vec<Weak<SequenceCallback>>)>>,
+}
+
+impl SequenceCallbacks {
+    pub fn push_sequence(&self, sequence: KeySequence, callback: Arc<SequenceCallback>) {
+        if let Ok(mut matcher) = self.matcher.lock() {
+            let callback = Arc::downgrade(&callback);
+            match matcher.get_mut(&sequence) {
+                Some((_, callbacks)) => callbacks.push(callback),
+                None => {
+                    matcher.insert(sequence.clone(), (sequence, vec![callback]));
+                }
+            }
+        }
+    }
+
+    /// Advances the sequences with this key press, which happened at `time`, and runs
+    /// the callbacks of the sequence it completes.
+    pub fn run_key_down(&self, event: &KeyEvent, time: Instant) {
+        if let Ok(mut matcher) = self.matcher.lock() {
+            matcher.retain(|(_, callbacks)| {
+                callbacks.retain(|callback| callback.upgrade().is_some());
+                !callbacks.is_empty()
+            });
+            if let SequenceMatch::Complete((sequence, callbacks)) = matcher.feed(event, time) {
+                for callback in callbacks {
+                    if let Some(callback) = callback.upgrade() {
+                        callback(sequence);
+                    }
+                }
+            }
+        }
+    }
+}
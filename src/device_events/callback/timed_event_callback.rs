@@ -0,0 +1,37 @@
+//! Timed event callback.
+
+use crate::device_events::utils;
+use std::ops::DerefMut;
+use std::sync::{Arc, Mutex, Weak};
+use TimedEvent;
+
+/// Timed event callback.
+pub type TimedEventCallback = dyn Fn(&TimedEvent) + Sync + Send + 'static;
+
+/// Timed event callbacks.
+#[derive(Default)]
+pub(crate) struct TimedEventCallbacks {
+    events: Mutex<Vec<Weak<TimedEventCallback>>>,
+}
+
+impl TimedEventCallbacks {
+    pub fn push_event(&self, callback: Arc<TimedEventCallback>) {
+        if let Ok(mut callbacks) = self.events.lock() {
+            let callback = Arc::downgrade(&callback);
+            callbacks.push(callback)
+        }
+    }
+
+    pub fn run_event(&self, event: &TimedEvent) {
+        if let Ok(mut callbacks) = self.events.lock() {
+            utils::DrainFilter::drain_filter(callbacks.deref_mut(), |callback| {
+                callback.upgrade().is_none()
+            });
+            for callback in callbacks.iter() {
+                if let Some(callback) = callback.upgrade() {
+                    callback(event);
+                }
+            }
+        }
+    }
+}
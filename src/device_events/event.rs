@@ -0,0 +1,55 @@
+//! Device event.
+
+use mouse_state::{HiResScroll, MouseScrollEvent};
+use std::time::Instant;
+use {Keycode, Modifiers, MouseButton, MouseDelta, MousePosition};
+
+/// A key press or release.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KeyEvent {
+    /// The key pressed or released.
+    pub key: Keycode,
+    /// The modifiers held and the lock keys toggled on right after the event.
+    pub modifiers: Modifiers,
+}
+
+/// Any event produced by the keyboard or the mouse.
+///
+/// This is what [`DeviceEventsHandler::subscribe`](crate::DeviceEventsHandler::subscribe)
+/// delivers, in the order the events were detected.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DeviceEvent {
+    /// A key was pressed.
+    KeyDown(KeyEvent),
+    /// A key was released.
+    KeyUp(KeyEvent),
+    /// A key press typed a character in the current keyboard layout.
+    /// Follows the [`DeviceEvent::KeyDown`] of that key.
+    Char(char),
+    /// The mouse moved to a new position.
+    MouseMove(MousePosition),
+    /// The mouse moved by a relative amount, see [`DeviceEvents::on_mouse_motion`](crate::DeviceEvents::on_mouse_motion).
+    MouseMotion(MouseDelta),
+    /// A mouse button was pressed.
+    MouseDown(MouseButton),
+    /// A mouse button was released.
+    MouseUp(MouseButton),
+    /// The mouse wheel was scrolled.
+    Scroll(MouseScrollEvent),
+    /// The mouse wheel or the touchpad scrolled, in high resolution, see
+    /// [`DeviceEvents::on_hi_res_scroll`](crate::DeviceEvents::on_hi_res_scroll).
+    HiResScroll(HiResScroll),
+}
+
+/// A [`DeviceEvent`] along with when it happened.
+///
+/// The time comes from the backend event when it has one, such as XInput2 on X11.
+/// Otherwise it is when the event was detected by polling the device state, which is
+/// late by up to the sleep duration of the event loop.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimedEvent {
+    /// When the event happened.
+    pub time: Instant,
+    /// The event.
+    pub event: DeviceEvent,
+}
@@ -1,34 +1,118 @@
-use super::{CallbackGuard, KeyboardCallbacks};
-use crate::mouse_state::{MouseScrollEvent, ScrollDelta};
-use std::sync::{Arc, LazyLock, Mutex, Weak};
-use std::thread::{sleep, spawn, JoinHandle};
-use std::time::Duration;
+#[cfg(feature = "async")]
+use super::DeviceEventStream;
+use super::{
+    CallbackGuard, DeviceEvent, EventCallbacks, Hotkey, KeyEvent, KeySequence, TimedEvent,
+};
+use crate::mouse_state::{HiResScroll, MouseScrollEvent, ScrollDelta};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{current, sleep, spawn, JoinHandle};
+use std::time::{Duration, Instant};
+use DeviceQuery;
+use Error;
+use Modifiers;
 use MouseState;
-use {DeviceQuery, MouseCallbacks};
 use {DeviceState, Keycode};
-use {MouseButton, MousePosition};
+use {MouseButton, MouseDelta, MousePosition};
+
+#[cfg(target_os = "linux")]
+use crate::device_state::{mouse_button_from_x11, Backend, RawEvent, RawEventListener};
 
 pub(crate) struct EventLoop {
-    keyboard_callbacks: Arc<KeyboardCallbacks>,
-    mouse_callbacks: Arc<MouseCallbacks>,
-    _keyboard_thread: JoinHandle<()>,
-    _mouse_thread: JoinHandle<()>,
+    callbacks: Arc<EventCallbacks>,
+    running: Arc<AtomicBool>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+/// What the event loop threads need to dispatch events and know when to stop.
+#[derive(Clone)]
+struct LoopHandle {
+    callbacks: Weak<EventCallbacks>,
+    running: Arc<AtomicBool>,
 }
 
-fn keyboard_thread(callbacks: Weak<KeyboardCallbacks>, sleep_dur: Duration) -> JoinHandle<()> {
+impl LoopHandle {
+    /// Returns the callbacks to dispatch to, or `None` once the event loop is stopping.
+    fn callbacks(&self) -> Option<Arc<EventCallbacks>> {
+        if !self.running.load(Ordering::Acquire) {
+            return None;
+        }
+        self.callbacks.upgrade()
+    }
+
+    /// Runs the error callbacks.
+    fn report(&self, error: &Error) {
+        if let Some(callbacks) = self.callbacks() {
+            callbacks.error.run_error(error);
+        }
+    }
+
+    /// Returns the result of a device query, reporting its error unless it is the same
+    /// as the previous one, so that a lasting failure isn't reported at every poll.
+    fn polled<T>(&self, result: Result<T, Error>, last_error: &mut Option<Error>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                *last_error = None;
+                Some(value)
+            }
+            Err(error) => {
+                if last_error.as_ref() != Some(&error) {
+                    self.report(&error);
+                    *last_error = Some(error);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Polls the device state every `sleep_dur`, dispatching the differences between two polls.
+fn polling_threads<Device: DeviceQuery + Send + Sync + 'static>(
+    device_state: Arc<Device>,
+    handle: LoopHandle,
+    sleep_dur: Duration,
+) -> Vec<JoinHandle<()>> {
+    vec![
+        keyboard_thread(device_state.clone(), handle.clone(), sleep_dur),
+        mouse_thread(device_state, handle, sleep_dur),
+    ]
+}
+
+fn keyboard_thread<Device: DeviceQuery + Send + Sync + 'static>(
+    device_state: Arc<Device>,
+    handle: LoopHandle,
+    sleep_dur: Duration,
+) -> JoinHandle<()> {
     spawn(move || {
-        let device_state = DeviceState::new();
         let mut prev_keys = vec![];
-        while let Some(callbacks) = callbacks.upgrade() {
-            let keys = device_state.get_keys();
+        let mut last_error = None;
+        while let Some(callbacks) = handle.callbacks() {
+            let Some(keys) = handle.polled(device_state.try_get_keys(), &mut last_error) else {
+                sleep(sleep_dur);
+                continue;
+            };
+            let time = Instant::now();
+            // Only query the modifiers when they are needed.
+            let modifiers = if keys != prev_keys {
+                device_state.get_modifiers()
+            } else {
+                Modifiers::empty()
+            };
             for key_state in &keys {
                 if !prev_keys.contains(key_state) {
-                    callbacks.run_key_down(key_state);
+                    let key = *key_state;
+                    callbacks.dispatch(DeviceEvent::KeyDown(KeyEvent { key, modifiers }), time);
+                    if let Some(character) = device_state.get_char(key) {
+                        callbacks.dispatch(DeviceEvent::Char(character), time);
+                    }
                 }
             }
             for key_state in &prev_keys {
                 if !keys.contains(key_state) {
-                    callbacks.run_key_up(key_state);
+                    let key = *key_state;
+                    callbacks.dispatch(DeviceEvent::KeyUp(KeyEvent { key, modifiers }), time);
                 }
             }
             prev_keys = keys;
@@ -37,27 +121,44 @@ fn keyboard_thread(callbacks: Weak<KeyboardCallbacks>, sleep_dur: Duration) -> J
     })
 }
 
-fn mouse_thread(callbacks: Weak<MouseCallbacks>, sleep_dur: Duration) -> JoinHandle<()> {
+fn mouse_thread<Device: DeviceQuery + Send + Sync + 'static>(
+    device_state: Arc<Device>,
+    handle: LoopHandle,
+    sleep_dur: Duration,
+) -> JoinHandle<()> {
     spawn(move || {
-        let device_state = DeviceState::new();
         let mut previous_mouse_state = MouseState::default();
         let mut accumulated_scroll = ScrollDelta::default();
-        while let Some(callbacks) = callbacks.upgrade() {
-            let mouse_state = device_state.get_mouse();
-            for (index, (previous_state, current_state)) in previous_mouse_state
+        let mut last_error = None;
+        while let Some(callbacks) = handle.callbacks() {
+            let Some(mouse_state) = handle.polled(device_state.try_get_mouse(), &mut last_error)
+            else {
+                sleep(sleep_dur);
+                continue;
+            };
+            let time = Instant::now();
+            let buttons = previous_mouse_state
                 .button_pressed
-                .iter()
-                .zip(mouse_state.button_pressed.iter())
-                .enumerate()
-            {
-                if !(*previous_state) && *current_state {
-                    callbacks.run_mouse_down(&index);
-                } else if *previous_state && !(*current_state) {
-                    callbacks.run_mouse_up(&index);
+                .len()
+                .max(mouse_state.button_pressed.len());
+            for button in (1..buttons).filter_map(MouseButton::from_number) {
+                let previous_state = previous_mouse_state.is_pressed(button);
+                let current_state = mouse_state.is_pressed(button);
+                if !previous_state && current_state {
+                    callbacks.dispatch(DeviceEvent::MouseDown(button), time);
+                } else if previous_state && !current_state {
+                    callbacks.dispatch(DeviceEvent::MouseUp(button), time);
                 }
             }
             if mouse_state.coords != previous_mouse_state.coords {
-                callbacks.run_mouse_move(&mouse_state.coords);
+                callbacks.dispatch(DeviceEvent::MouseMove(mouse_state.coords), time);
+            }
+            if mouse_state.motion_delta != (0, 0) {
+                callbacks.dispatch(DeviceEvent::MouseMotion(mouse_state.motion_delta), time);
+            }
+
+            if !mouse_state.hi_res_scroll.is_zero() {
+                callbacks.dispatch(DeviceEvent::HiResScroll(mouse_state.hi_res_scroll), time);
             }
 
             // Accumulate scroll delta
@@ -71,7 +172,7 @@ fn mouse_thread(callbacks: Weak<MouseCallbacks>, sleep_dur: Duration) -> JoinHan
                 } else {
                     MouseScrollEvent::VerticalDown
                 };
-                callbacks.run_mouse_scroll(&event);
+                callbacks.dispatch(DeviceEvent::Scroll(event), time);
                 accumulated_scroll.vertical = 0;
             }
 
@@ -81,7 +182,7 @@ fn mouse_thread(callbacks: Weak<MouseCallbacks>, sleep_dur: Duration) -> JoinHan
                 } else {
                     MouseScrollEvent::HorizontalLeft
                 };
-                callbacks.run_mouse_scroll(&event);
+                callbacks.dispatch(DeviceEvent::Scroll(event), time);
                 accumulated_scroll.horizontal = 0;
             }
 
@@ -91,90 +192,340 @@ fn mouse_thread(callbacks: Weak<MouseCallbacks>, sleep_dur: Duration) -> JoinHan
     })
 }
 
-impl Default for EventLoop {
-    fn default() -> Self {
-        Self::new(Duration::from_micros(100))
-    }
+/// How long the XInput2 thread waits for events before checking if it should stop.
+#[cfg(target_os = "linux")]
+const RAW_EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Dispatches XInput2 raw events as they arrive instead of polling the device state.
+#[cfg(target_os = "linux")]
+fn xinput2_thread(mut listener: RawEventListener, handle: LoopHandle) -> JoinHandle<()> {
+    spawn(move || {
+        let mut pressed_keys = vec![];
+        let mut pressed_buttons = vec![];
+        let mut previous_position = listener.pointer_position();
+        loop {
+            let events = match listener.next_events(RAW_EVENT_TIMEOUT) {
+                Ok(events) => events,
+                Err(error) => return handle.report(&error),
+            };
+            let Some(callbacks) = handle.callbacks() else {
+                break;
+            };
+            let mut moved = None;
+            for (event, time) in events {
+                let dispatch = |event| callbacks.dispatch(event, time);
+                match event {
+                    RawEvent::KeyDown(key) => {
+                        if !pressed_keys.contains(&key) {
+                            pressed_keys.push(key);
+                            let modifiers =
+                                Modifiers::from_keys(&pressed_keys) | listener.lock_modifiers();
+                            dispatch(DeviceEvent::KeyDown(KeyEvent { key, modifiers }));
+                            if let Some(character) = listener.key_char(key) {
+                                dispatch(DeviceEvent::Char(character));
+                            }
+                        }
+                    }
+                    RawEvent::KeyUp(key) => {
+                        if let Some(index) = pressed_keys.iter().position(|k| *k == key) {
+                            pressed_keys.remove(index);
+                            let modifiers =
+                                Modifiers::from_keys(&pressed_keys) | listener.lock_modifiers();
+                            dispatch(DeviceEvent::KeyUp(KeyEvent { key, modifiers }));
+                        }
+                    }
+                    // In X11, buttons 4/5 are scroll up/down, buttons 6/7 are scroll left/right.
+                    RawEvent::ButtonDown(4) => {
+                        dispatch(DeviceEvent::Scroll(MouseScrollEvent::VerticalUp))
+                    }
+                    RawEvent::ButtonDown(5) => {
+                        dispatch(DeviceEvent::Scroll(MouseScrollEvent::VerticalDown))
+                    }
+                    RawEvent::ButtonDown(6) => {
+                        dispatch(DeviceEvent::Scroll(MouseScrollEvent::HorizontalLeft))
+                    }
+                    RawEvent::ButtonDown(7) => {
+                        dispatch(DeviceEvent::Scroll(MouseScrollEvent::HorizontalRight))
+                    }
+                    RawEvent::ButtonUp(4..=7) => {}
+                    RawEvent::ButtonDown(button) => {
+                        let Some(button) = mouse_button_from_x11(button) else {
+                            continue;
+                        };
+                        if !pressed_buttons.contains(&button) {
+                            pressed_buttons.push(button);
+                            dispatch(DeviceEvent::MouseDown(button));
+                        }
+                    }
+                    RawEvent::ButtonUp(button) => {
+                        let Some(button) = mouse_button_from_x11(button) else {
+                            continue;
+                        };
+                        if let Some(index) = pressed_buttons.iter().position(|b| *b == button) {
+                            pressed_buttons.remove(index);
+                            dispatch(DeviceEvent::MouseUp(button));
+                        }
+                    }
+                    RawEvent::Motion(delta) => {
+                        if delta != (0, 0) {
+                            dispatch(DeviceEvent::MouseMotion(delta));
+                        }
+                        moved = Some(time);
+                    }
+                    RawEvent::Moved => moved = Some(time),
+                    RawEvent::Scroll(scroll) => dispatch(DeviceEvent::HiResScroll(scroll)),
+                }
+            }
+            // Raw motion comes in bursts, only query the resulting position once.
+            if let Some(time) = moved {
+                let position = listener.pointer_position();
+                if position != previous_position {
+                    callbacks.dispatch(DeviceEvent::MouseMove(position), time);
+                    previous_position = position;
+                }
+            }
+        }
+    })
 }
 
 impl EventLoop {
-    fn new(sleep_dur: Duration) -> Self {
-        let keyboard_callbacks = Arc::new(KeyboardCallbacks::default());
-        let mouse_callbacks = Arc::new(MouseCallbacks::default());
-        let _keyboard_thread = keyboard_thread(Arc::downgrade(&keyboard_callbacks), sleep_dur);
-        let _mouse_thread = mouse_thread(Arc::downgrade(&mouse_callbacks), sleep_dur);
+    /// On X11, listens to XInput2 raw events when the server supports them, so no press
+    /// is missed between two polls. Otherwise polls the device state every `sleep_dur`.
+    pub fn new(sleep_dur: Duration) -> Result<Self, Error> {
+        #[cfg(target_os = "linux")]
+        if Backend::default() == Backend::X11 {
+            if let Ok(listener) = RawEventListener::open(None) {
+                return Ok(Self::start(|handle| vec![xinput2_thread(listener, handle)]));
+            }
+        }
+        Ok(Self::with_device(DeviceState::try_new()?, sleep_dur))
+    }
+
+    /// Same as `new` on the given X display, which fails with `Error::NoDisplay` when it
+    /// can't be reached rather than falling back to another backend.
+    #[cfg(target_os = "linux")]
+    pub fn with_display(display_name: &str, sleep_dur: Duration) -> Result<Self, Error> {
+        if let Ok(listener) = RawEventListener::open(Some(display_name)) {
+            return Ok(Self::start(|handle| vec![xinput2_thread(listener, handle)]));
+        }
+        Ok(Self::with_device(
+            DeviceState::try_with_display(display_name)?,
+            sleep_dur,
+        ))
+    }
+
+    /// Polls the given device state every `sleep_dur`.
+    pub fn with_device<Device: DeviceQuery + Send + Sync + 'static>(
+        device_state: Device,
+        sleep_dur: Duration,
+    ) -> Self {
+        let device_state = Arc::new(device_state);
+        Self::start(|handle| polling_threads(device_state, handle, sleep_dur))
+    }
+
+    fn start<F: FnOnce(LoopHandle) -> Vec<JoinHandle<()>>>(spawn_threads: F) -> Self {
+        let callbacks = Arc::new(EventCallbacks::default());
+        let handle = LoopHandle {
+            callbacks: Arc::downgrade(&callbacks),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let running = handle.running.clone();
+        let threads = Mutex::new(spawn_threads(handle));
         Self {
-            keyboard_callbacks,
-            mouse_callbacks,
-            _keyboard_thread,
-            _mouse_thread,
+            callbacks,
+            running,
+            threads,
         }
     }
 
+    /// Stops the event loop threads and waits for them to exit. Subscriptions end and
+    /// newly registered callbacks are never called.
+    pub fn shutdown(&self) {
+        self.running.store(false, Ordering::Release);
+        self.callbacks.clear_subscribers();
+        let threads = match self.threads.lock() {
+            Ok(mut threads) => mem::take(&mut *threads),
+            Err(_) => return,
+        };
+        for thread in threads {
+            // The event loop can be dropped from one of its own callbacks.
+            if thread.thread().id() != current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    /// Dispatches an event from outside the event loop threads. Returns false once the
+    /// event loop is stopped.
+    pub fn dispatch(&self, event: DeviceEvent, time: Instant) -> bool {
+        if !self.running.load(Ordering::Acquire) {
+            return false;
+        }
+        self.callbacks.dispatch(event, time);
+        true
+    }
+
+    pub fn on_error<Callback: Fn(&Error) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.error.push_error(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_event<Callback: Fn(&TimedEvent) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.events.push_event(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn subscribe(&self) -> Receiver<DeviceEvent> {
+        let (sender, receiver) = channel();
+        self.callbacks.push_subscriber(Box::new(sender));
+        receiver
+    }
+
+    pub fn subscribe_timed(&self) -> Receiver<TimedEvent> {
+        let (sender, receiver) = channel();
+        self.callbacks.push_subscriber(Box::new(sender));
+        receiver
+    }
+
+    #[cfg(feature = "async")]
+    pub fn subscribe_stream(&self, capacity: usize) -> DeviceEventStream {
+        let (subscriber, stream) = DeviceEventStream::new(capacity);
+        self.callbacks.push_subscriber(Box::new(subscriber));
+        stream
+    }
+
     pub fn on_key_down<Callback: Fn(&Keycode) + Send + Sync + 'static>(
-        &mut self,
+        &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.keyboard_callbacks.push_key_down(_callback.clone());
+        self.callbacks.keyboard.push_key_down(_callback.clone());
         CallbackGuard { _callback }
     }
 
     pub fn on_key_up<Callback: Fn(&Keycode) + Send + Sync + 'static>(
-        &mut self,
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.keyboard.push_key_up(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_key_down_event<Callback: Fn(&KeyEvent) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.keyboard.push_key_down_event(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_key_up_event<Callback: Fn(&KeyEvent) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.keyboard.push_key_up_event(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_char<Callback: Fn(&char) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.keyboard.push_char(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_hotkey<Callback: Fn(&Hotkey) + Send + Sync + 'static>(
+        &self,
+        hotkey: Hotkey,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.keyboard_callbacks.push_key_up(_callback.clone());
+        self.callbacks.hotkeys.push_hotkey(hotkey, _callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_key_sequence<Callback: Fn(&KeySequence) + Send + Sync + 'static>(
+        &self,
+        sequence: KeySequence,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks
+            .sequences
+            .push_sequence(sequence, _callback.clone());
         CallbackGuard { _callback }
     }
 
     pub fn on_mouse_move<Callback: Fn(&MousePosition) + Send + Sync + 'static>(
-        &mut self,
+        &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.mouse_callbacks.push_mouse_move(_callback.clone());
+        self.callbacks.mouse.push_mouse_move(_callback.clone());
+        CallbackGuard { _callback }
+    }
+
+    pub fn on_mouse_motion<Callback: Fn(&MouseDelta) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.mouse.push_mouse_motion(_callback.clone());
         CallbackGuard { _callback }
     }
 
     pub fn on_mouse_up<Callback: Fn(&MouseButton) + Send + Sync + 'static>(
-        &mut self,
+        &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.mouse_callbacks.push_mouse_up(_callback.clone());
+        self.callbacks.mouse.push_mouse_up(_callback.clone());
         CallbackGuard { _callback }
     }
 
     pub fn on_mouse_down<Callback: Fn(&MouseButton) + Send + Sync + 'static>(
-        &mut self,
+        &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.mouse_callbacks.push_mouse_down(_callback.clone());
+        self.callbacks.mouse.push_mouse_down(_callback.clone());
         CallbackGuard { _callback }
     }
 
     pub fn on_mouse_scroll<Callback: Fn(&MouseScrollEvent) + Send + Sync + 'static>(
-        &mut self,
+        &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
         let _callback = Arc::new(callback);
-        self.mouse_callbacks.push_mouse_scroll(_callback.clone());
+        self.callbacks.mouse.push_mouse_scroll(_callback.clone());
         CallbackGuard { _callback }
     }
-}
 
-pub(crate) static EVENT_LOOP: LazyLock<Mutex<Option<EventLoop>>> = LazyLock::new(|| Default::default());
+    pub fn on_hi_res_scroll<Callback: Fn(&HiResScroll) + Send + Sync + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        let _callback = Arc::new(callback);
+        self.callbacks.mouse.push_hi_res_scroll(_callback.clone());
+        CallbackGuard { _callback }
+    }
+}
 
-pub(crate) fn init_event_loop(sleep_dur: Duration) -> bool {
-    let Ok(mut lock) = EVENT_LOOP.lock() else {
-        return false;
-    };
-    if lock.is_some() {
-        return false;
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        self.shutdown();
     }
-    *lock = Some(EventLoop::new(sleep_dur));
-    true
-}
\ No newline at end of file
+}
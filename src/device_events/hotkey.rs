@@ -0,0 +1,147 @@
+//! Keyboard shortcuts.
+
+use super::KeyEvent;
+use std::fmt;
+use std::str::FromStr;
+use {Keycode, Modifiers};
+
+/// The modifiers a hotkey can require, and their names in accelerator strings.
+const MODIFIER_NAMES: [(Modifiers, &str); 4] = [
+    (Modifiers::CONTROL, "Ctrl"),
+    (Modifiers::SHIFT, "Shift"),
+    (Modifiers::ALT, "Alt"),
+    (Modifiers::META, "Meta"),
+];
+
+const LEFT_MODIFIERS: Modifiers = Modifiers::LSHIFT
+    .union(Modifiers::LCONTROL)
+    .union(Modifiers::LALT)
+    .union(Modifiers::LMETA);
+
+/// A key pressed while holding some modifiers, such as `Ctrl+Shift+F9`.
+///
+/// It is parsed from an accelerator string: the modifiers and then the key, separated
+/// by `+`. The modifiers are `Ctrl` (or `Control`), `Shift`, `Alt` (or `Option`) and
+/// `Meta` (or `Super`, `Win`, `Cmd`, `Command`), prefixed by `L` or `R` to only accept
+/// one side, as in `RCtrl`. The key is a [`Keycode`] name, a letter or a digit. Case
+/// doesn't matter.
+///
+/// ```
+/// use device_query::{Hotkey, Keycode, Modifiers};
+///
+/// let hotkey: Hotkey = "Ctrl+Alt+Delete".parse().unwrap();
+/// assert_eq!(hotkey, Hotkey::new(Modifiers::CONTROL | Modifiers::ALT, Keycode::Delete));
+/// assert_eq!(hotkey.to_string(), "Ctrl+Alt+Delete");
+/// assert_eq!("shift+rctrl+f9".parse(), Ok(Hotkey::new(Modifiers::SHIFT | Modifiers::RCONTROL, Keycode::F9)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    /// The modifiers to hold. Flags for both sides, such as `Modifiers::CONTROL`, are
+    /// satisfied by either side.
+    pub modifiers: Modifiers,
+    /// The key to press.
+    pub key: Keycode,
+}
+
+impl Hotkey {
+    /// Creates a hotkey from its modifiers and key.
+    pub fn new(modifiers: Modifiers, key: Keycode) -> Hotkey {
+        Hotkey { modifiers, key }
+    }
+
+    /// Returns true if the key press activates this hotkey: it is the press of its key
+    /// while holding its modifiers and no other ones. Lock keys are ignored.
+    ///
+    /// ```
+    /// use device_query::{Hotkey, KeyEvent, Keycode, Modifiers};
+    ///
+    /// let hotkey: Hotkey = "Ctrl+S".parse().unwrap();
+    /// let press = |modifiers| KeyEvent { key: Keycode::S, modifiers };
+    /// assert!(hotkey.matches(&press(Modifiers::RCONTROL | Modifiers::CAPS_LOCK)));
+    /// assert!(!hotkey.matches(&press(Modifiers::LCONTROL | Modifiers::LSHIFT)));
+    /// assert!(!hotkey.matches(&press(Modifiers::empty())));
+    /// ```
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        // A modifier key used as the hotkey key doesn't count as held modifier.
+        let held = event.modifiers - Modifiers::from_key(self.key);
+        event.key == self.key
+            && MODIFIER_NAMES.iter().all(|(group, _)| {
+                let required = self.modifiers & *group;
+                let held = held & *group;
+                if required == *group {
+                    !held.is_empty()
+                } else {
+                    held == required
+                }
+            })
+    }
+}
+
+/// Parses a modifier of an accelerator string.
+fn parse_modifier(name: &str) -> Option<Modifiers> {
+    let name = name.to_ascii_lowercase();
+    let group = |name: &str| match name {
+        "ctrl" | "control" => Some(Modifiers::CONTROL),
+        "shift" => Some(Modifiers::SHIFT),
+        "alt" | "option" => Some(Modifiers::ALT),
+        "meta" | "super" | "win" | "cmd" | "command" => Some(Modifiers::META),
+        _ => None,
+    };
+    if let Some(modifiers) = group(&name) {
+        return Some(modifiers);
+    }
+    if let Some(name) = name.strip_prefix('l') {
+        return group(name).map(|modifiers| modifiers & LEFT_MODIFIERS);
+    }
+    if let Some(name) = name.strip_prefix('r') {
+        return group(name).map(|modifiers| modifiers - LEFT_MODIFIERS);
+    }
+    None
+}
+
+/// Parses the key of an accelerator string.
+fn parse_key(name: &str) -> Option<Keycode> {
+    if let Ok(key) = Keycode::from_str(name) {
+        return Some(key);
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "esc" => Some(Keycode::Escape),
+        "del" => Some(Keycode::Delete),
+        "return" => Some(Keycode::Enter),
+        digit if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => {
+            Keycode::from_str(&format!("Key{}", digit)).ok()
+        }
+        name => Keycode::iter().find(|key| key.to_string().eq_ignore_ascii_case(name)),
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        let key = parse_key(key).ok_or_else(|| format!("unknown hotkey key: {:?}", key))?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= parse_modifier(part)
+                .ok_or_else(|| format!("unknown hotkey modifier: {:?}", part))?;
+        }
+        Ok(Hotkey { modifiers, key })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (group, name) in MODIFIER_NAMES.iter() {
+            let modifiers = self.modifiers & *group;
+            if modifiers == *group {
+                write!(f, "{}+", name)?;
+            } else if modifiers.intersects(LEFT_MODIFIERS) {
+                write!(f, "L{}+", name)?;
+            } else if !modifiers.is_empty() {
+                write!(f, "R{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
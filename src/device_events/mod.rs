@@ -25,22 +25,49 @@
 //! 
 
 mod callback;
+mod event;
 mod event_loop;
+mod hotkey;
+mod recording;
+mod sequence;
+#[cfg(feature = "async")]
+mod stream;
 mod utils;
 
-use std::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::mouse_state::MouseScrollEvent;
-use crate::MousePosition;
+use crate::mouse_state::{HiResScroll, MouseScrollEvent};
+use crate::{MouseDelta, MousePosition};
 
 pub use self::callback::*;
+pub use self::event::*;
+pub use self::hotkey::*;
+pub use self::recording::*;
+pub use self::sequence::*;
+#[cfg(feature = "async")]
+pub use self::stream::*;
 use self::event_loop::*;
 
+use DeviceQuery;
+use Error;
 use Keycode;
 use MouseButton;
 
+mod private {
+    /// Keeps [`DeviceEvents`](super::DeviceEvents) from being implemented outside of this
+    /// crate, so that registrations can be added to it without breaking implementors.
+    pub trait Sealed {}
+
+    impl Sealed for super::DeviceEventsHandler {}
+}
+
 /// All the supported devices events.
-pub trait DeviceEvents {
+///
+/// This trait is sealed: it is implemented by [`DeviceEventsHandler`] and can't be
+/// implemented outside of this crate.
+pub trait DeviceEvents: private::Sealed {
     /// Register an on key down event callback.
     fn on_key_down<Callback: Fn(&Keycode) + Sync + Send + 'static>(
         &self,
@@ -51,12 +78,76 @@ pub trait DeviceEvents {
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback>;
+    /// Register an on key down event callback getting the modifiers along with the key.
+    fn on_key_down_event<Callback: Fn(&KeyEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+    /// Register an on key up event callback getting the modifiers along with the key.
+    fn on_key_up_event<Callback: Fn(&KeyEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+    /// Register a callback getting the character typed by each key press, as translated
+    /// by the current keyboard layout. See [`DeviceQuery::get_char`].
+    fn on_char<Callback: Fn(&char) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+
+    /// Register a callback for a global hotkey. It is called once each time the hotkey
+    /// is pressed, and not again until its key is released.
+    ///
+    /// ```no_run
+    /// use device_query::{DeviceEvents, DeviceEventsHandler};
+    /// use std::time::Duration;
+    ///
+    /// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+    /// let _guard = device_events.on_hotkey("Ctrl+Shift+F9".parse().unwrap(), |hotkey| {
+    ///     println!("{} pressed", hotkey);
+    /// });
+    /// ```
+    fn on_hotkey<Callback: Fn(&Hotkey) + Sync + Send + 'static>(
+        &self,
+        hotkey: Hotkey,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+
+    /// Register a callback for a key sequence, such as `g g`. It is called when its last
+    /// step is pressed, provided each step followed the previous one within
+    /// [`SequenceMatcher::DEFAULT_TIMEOUT`].
+    ///
+    /// ```no_run
+    /// use device_query::{DeviceEvents, DeviceEventsHandler};
+    /// use std::time::Duration;
+    ///
+    /// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+    /// let _guard = device_events.on_key_sequence("Ctrl+X Ctrl+S".parse().unwrap(), |sequence| {
+    ///     println!("{} pressed", sequence);
+    /// });
+    /// ```
+    fn on_key_sequence<Callback: Fn(&KeySequence) + Sync + Send + 'static>(
+        &self,
+        sequence: KeySequence,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
 
     /// Register an on mouse move event callback.
     fn on_mouse_move<Callback: Fn(&MousePosition) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback>;
+    /// Register a callback for the relative motion of the mouse, in device units and
+    /// without the pointer acceleration. Unlike [`on_mouse_move`](Self::on_mouse_move),
+    /// motion keeps being reported when the cursor is stuck against a screen edge or
+    /// confined by a game.
+    ///
+    /// Only available on Linux, from XInput2 raw motion on X11 or `REL_X`/`REL_Y` with
+    /// the evdev backend.
+    fn on_mouse_motion<Callback: Fn(&MouseDelta) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
     /// Register an on mouse button down event callback.
     fn on_mouse_down<Callback: Fn(&MouseButton) + Sync + Send + 'static>(
         &self,
@@ -72,30 +163,162 @@ pub trait DeviceEvents {
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback>;
+    /// Register a callback for high-resolution scrolling, in 1/120 of a notch, which
+    /// reports the fractions of notches of touchpads and high-resolution wheels that
+    /// [`on_mouse_scroll`](Self::on_mouse_scroll) misses.
+    ///
+    /// On X11 the movement comes from the smooth scrolling valuators, with the evdev
+    /// backend from `REL_WHEEL_HI_RES`. Windows can't tell touchpads from wheels, so the
+    /// source is always [`ScrollSource::Wheel`](crate::ScrollSource::Wheel) there.
+    fn on_hi_res_scroll<Callback: Fn(&HiResScroll) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+
+    /// Register a callback getting every event along with when it happened.
+    ///
+    /// ```no_run
+    /// use device_query::{DeviceEvents, DeviceEventsHandler};
+    /// use std::time::Duration;
+    ///
+    /// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+    /// let _guard = device_events.on_event(|event| {
+    ///     println!("{:?} {:?} ago", event.event, event.time.elapsed());
+    /// });
+    /// ```
+    fn on_event<Callback: Fn(&TimedEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
+
+    /// Register a callback for the errors reading the devices, such as a lost connection
+    /// or an unplugged device.
+    ///
+    /// The XInput2 event loop of X11 stops on them. The polling event loops, used on
+    /// Windows, macOS, with the evdev backend and [`DeviceEventsHandler::with_device_query`],
+    /// keep polling and report an error once until the devices can be read again, see
+    /// [`DeviceQuery::try_get_mouse`].
+    fn on_error<Callback: Fn(&Error) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback>;
 }
 
-pub struct DeviceEventsHandler;
+/// Handle on an event loop. Every handler created with [`DeviceEventsHandler::new`] runs
+/// its own event loop with its own callbacks; clones share the same event loop, which stops
+/// once the last of them is dropped.
+#[derive(Clone)]
+pub struct DeviceEventsHandler {
+    event_loop: Arc<EventLoop>,
+}
 
 impl DeviceEventsHandler {
-    /// Attempts to start event loop with the given sleep duration.
-    /// Returns None if the event loop is already running.
-    pub fn new(sleep_dur: Duration) -> Option<Self> {
-        event_loop::init_event_loop(sleep_dur).then_some(DeviceEventsHandler)
+    /// Starts a new event loop with the given sleep duration.
+    /// Fails if the devices can't be queried, see [`DeviceState::try_new`](crate::DeviceState::try_new).
+    ///
+    /// On Linux with X11, events are read from XInput2 as they happen when the server
+    /// supports it, and the sleep duration is only used as a fallback polling interval.
+    pub fn new(sleep_dur: Duration) -> Result<Self, Error> {
+        Ok(DeviceEventsHandler {
+            event_loop: Arc::new(EventLoop::new(sleep_dur)?),
+        })
     }
-}
 
-/// Returns the event loop.
-///
-/// This is a workaround to avoid using unsafe code,
-/// the existence of a [`DeviceEventsHandler`] means that the event loop is already initialized.
-macro_rules! get_event_loop {
-    () => {
-        EVENT_LOOP
-            .lock()
-            .expect("Couldn't lock EVENT_LOOP")
-            .as_mut()
-            .unwrap()
-    };
+    /// Starts a new event loop on the given X display, such as `":1"`, instead of the one
+    /// in `$DISPLAY`. Fails with [`Error::NoDisplay`] when the display can't be reached.
+    #[cfg(target_os = "linux")]
+    pub fn with_display(display_name: &str, sleep_dur: Duration) -> Result<Self, Error> {
+        Ok(DeviceEventsHandler {
+            event_loop: Arc::new(EventLoop::with_display(display_name, sleep_dur)?),
+        })
+    }
+
+    /// Starts a new event loop polling the given device state every `sleep_dur`, instead
+    /// of the devices of the platform. Useful to test the callbacks with a
+    /// [`MockDeviceState`](crate::MockDeviceState):
+    ///
+    /// ```
+    /// use device_query::{DeviceControl, DeviceEvents, DeviceEventsHandler, Keycode, MockDeviceState};
+    /// use std::sync::mpsc::channel;
+    /// use std::time::Duration;
+    ///
+    /// let device_state = MockDeviceState::new();
+    /// let device_events =
+    ///     DeviceEventsHandler::with_device_query(device_state.clone(), Duration::from_millis(1));
+    /// let (sender, receiver) = channel();
+    /// let _guard = device_events.on_key_down(move |key| sender.send(*key).unwrap());
+    ///
+    /// device_state.key_down(Keycode::Space).unwrap();
+    /// assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(Keycode::Space));
+    /// ```
+    pub fn with_device_query<Device: DeviceQuery + Send + Sync + 'static>(
+        device_state: Device,
+        sleep_dur: Duration,
+    ) -> Self {
+        DeviceEventsHandler {
+            event_loop: Arc::new(EventLoop::with_device(device_state, sleep_dur)),
+        }
+    }
+
+    /// Stops the event loop and waits for its threads to exit, closing their connections
+    /// to the devices. Subscriptions end and callbacks are no longer called. Clones of this
+    /// handler share the event loop and are stopped as well.
+    ///
+    /// This also happens automatically when the last clone of the handler is dropped.
+    pub fn shutdown(&self) {
+        self.event_loop.shutdown()
+    }
+
+    /// Dispatches an event as if it happened at `time`. Returns false once the event loop
+    /// is stopped.
+    pub(crate) fn dispatch(&self, event: DeviceEvent, time: Instant) -> bool {
+        self.event_loop.dispatch(event, time)
+    }
+
+    /// Returns a receiver getting every [`DeviceEvent`] in the order they were detected.
+    /// The subscription ends when the receiver is dropped.
+    ///
+    /// ```no_run
+    /// use device_query::{DeviceEvent, DeviceEventsHandler};
+    /// use std::time::Duration;
+    ///
+    /// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+    /// for event in device_events.subscribe() {
+    ///     match event {
+    ///         DeviceEvent::KeyDown(event) => println!("Key down: {:?}", event.key),
+    ///         DeviceEvent::MouseMove(position) => println!("Mouse position: {:?}", position),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn subscribe(&self) -> Receiver<DeviceEvent> {
+        self.event_loop.subscribe()
+    }
+
+    /// Same as [`subscribe`](Self::subscribe), with the time each event happened.
+    pub fn subscribe_timed(&self) -> Receiver<TimedEvent> {
+        self.event_loop.subscribe_timed()
+    }
+
+    /// Returns a [`Stream`](futures_core::Stream) of every [`DeviceEvent`], buffering up
+    /// to `capacity` events. See [`DeviceEventStream`] for what happens when it is full.
+    ///
+    /// ```no_run
+    /// # extern crate device_query;
+    /// # extern crate futures;
+    /// use device_query::DeviceEventsHandler;
+    /// use futures::executor::block_on_stream;
+    /// use std::time::Duration;
+    ///
+    /// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+    /// for event in block_on_stream(device_events.subscribe_stream(256)) {
+    ///     println!("{:?}", event);
+    /// }
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn subscribe_stream(&self, capacity: usize) -> DeviceEventStream {
+        self.event_loop.subscribe_stream(capacity)
+    }
 }
 
 impl DeviceEvents for DeviceEventsHandler {
@@ -103,41 +326,106 @@ impl DeviceEvents for DeviceEventsHandler {
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_key_down(callback)
+        self.event_loop.on_key_down(callback)
     }
 
     fn on_key_up<Callback: Fn(&Keycode) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_key_up(callback)
+        self.event_loop.on_key_up(callback)
+    }
+
+    fn on_key_down_event<Callback: Fn(&KeyEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_key_down_event(callback)
+    }
+
+    fn on_key_up_event<Callback: Fn(&KeyEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_key_up_event(callback)
+    }
+
+    fn on_char<Callback: Fn(&char) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_char(callback)
+    }
+
+    fn on_hotkey<Callback: Fn(&Hotkey) + Sync + Send + 'static>(
+        &self,
+        hotkey: Hotkey,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_hotkey(hotkey, callback)
+    }
+
+    fn on_key_sequence<Callback: Fn(&KeySequence) + Sync + Send + 'static>(
+        &self,
+        sequence: KeySequence,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_key_sequence(sequence, callback)
     }
 
     fn on_mouse_move<Callback: Fn(&MousePosition) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_mouse_move(callback)
+        self.event_loop.on_mouse_move(callback)
+    }
+
+    fn on_mouse_motion<Callback: Fn(&MouseDelta) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_mouse_motion(callback)
     }
 
     fn on_mouse_down<Callback: Fn(&MouseButton) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_mouse_down(callback)
+        self.event_loop.on_mouse_down(callback)
     }
 
     fn on_mouse_up<Callback: Fn(&MouseButton) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_mouse_up(callback)
+        self.event_loop.on_mouse_up(callback)
     }
 
     fn on_mouse_scroll<Callback: Fn(&MouseScrollEvent) + Sync + Send + 'static>(
         &self,
         callback: Callback,
     ) -> CallbackGuard<Callback> {
-        get_event_loop!().on_mouse_scroll(callback)
+        self.event_loop.on_mouse_scroll(callback)
+    }
+
+    fn on_hi_res_scroll<Callback: Fn(&HiResScroll) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_hi_res_scroll(callback)
+    }
+
+    fn on_event<Callback: Fn(&TimedEvent) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_event(callback)
+    }
+
+    fn on_error<Callback: Fn(&Error) + Sync + Send + 'static>(
+        &self,
+        callback: Callback,
+    ) -> CallbackGuard<Callback> {
+        self.event_loop.on_error(callback)
     }
 }
@@ -0,0 +1,239 @@
+//! Binary format of recordings.
+
+use super::{RecordedEvent, Recording};
+use mouse_state::{HiResScroll, MouseButton, MouseScrollEvent, ScrollSource};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::time::Duration;
+use {DeviceEvent, KeyEvent, Keycode, Modifiers};
+
+const MAGIC: &[u8; 5] = b"DQREC";
+const VERSION: u8 = 1;
+
+const KEY_DOWN: u8 = 0;
+const KEY_UP: u8 = 1;
+const CHAR: u8 = 2;
+const MOUSE_MOVE: u8 = 3;
+const MOUSE_DOWN: u8 = 4;
+const MOUSE_UP: u8 = 5;
+const SCROLL: u8 = 6;
+const MOUSE_MOTION: u8 = 7;
+const HI_RES_SCROLL: u8 = 8;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(super) fn write_recording<W: Write>(writer: &mut W, recording: &Recording) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    let mut previous = Duration::ZERO;
+    for event in &recording.events {
+        let delta = event.offset.saturating_sub(previous);
+        previous = event.offset;
+        write_varint(writer, delta.as_micros() as u64)?;
+        write_event(writer, &event.event)?;
+    }
+    Ok(())
+}
+
+fn write_event<W: Write>(writer: &mut W, event: &DeviceEvent) -> io::Result<()> {
+    match *event {
+        DeviceEvent::KeyDown(ref key) => {
+            writer.write_all(&[KEY_DOWN])?;
+            write_key(writer, key)
+        }
+        DeviceEvent::KeyUp(ref key) => {
+            writer.write_all(&[KEY_UP])?;
+            write_key(writer, key)
+        }
+        DeviceEvent::Char(character) => {
+            writer.write_all(&[CHAR])?;
+            writer.write_all(&(character as u32).to_le_bytes())
+        }
+        DeviceEvent::MouseMove((x, y)) => {
+            writer.write_all(&[MOUSE_MOVE])?;
+            write_signed(writer, x)?;
+            write_signed(writer, y)
+        }
+        DeviceEvent::MouseMotion((dx, dy)) => {
+            writer.write_all(&[MOUSE_MOTION])?;
+            write_signed(writer, dx)?;
+            write_signed(writer, dy)
+        }
+        DeviceEvent::MouseDown(button) => {
+            writer.write_all(&[MOUSE_DOWN])?;
+            write_varint(writer, button.number() as u64)
+        }
+        DeviceEvent::MouseUp(button) => {
+            writer.write_all(&[MOUSE_UP])?;
+            write_varint(writer, button.number() as u64)
+        }
+        DeviceEvent::Scroll(ref direction) => {
+            let direction = match direction {
+                MouseScrollEvent::VerticalUp => 0,
+                MouseScrollEvent::VerticalDown => 1,
+                MouseScrollEvent::HorizontalRight => 2,
+                MouseScrollEvent::HorizontalLeft => 3,
+            };
+            writer.write_all(&[SCROLL, direction])
+        }
+        DeviceEvent::HiResScroll(ref scroll) => {
+            writer.write_all(&[HI_RES_SCROLL])?;
+            write_signed(writer, scroll.vertical)?;
+            write_signed(writer, scroll.horizontal)?;
+            let source = match scroll.source {
+                ScrollSource::Wheel => 0,
+                ScrollSource::Continuous => 1,
+            };
+            writer.write_all(&[source])
+        }
+    }
+}
+
+fn write_key<W: Write>(writer: &mut W, event: &KeyEvent) -> io::Result<()> {
+    let name = event.key.to_string();
+    writer.write_all(&[name.len() as u8])?;
+    writer.write_all(name.as_bytes())?;
+    writer.write_all(&event.modifiers.bits().to_le_bytes())
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_signed<W: Write>(writer: &mut W, value: i32) -> io::Result<()> {
+    write_varint(writer, ((value << 1) ^ (value >> 31)) as u32 as u64)
+}
+
+pub(super) fn read_recording<R: Read>(reader: &mut R) -> io::Result<Recording> {
+    let mut header = [0; 6];
+    reader.read_exact(&mut header)?;
+    if &header[..5] != MAGIC {
+        return Err(invalid_data("not a device_query recording"));
+    }
+    if header[5] != VERSION {
+        return Err(invalid_data("unsupported recording version"));
+    }
+    let mut events = vec![];
+    let mut offset = Duration::ZERO;
+    // The events end where a delta would start.
+    while let Some(delta) = read_first_byte(reader)? {
+        offset = offset
+            .checked_add(Duration::from_micros(read_varint_from(reader, delta)?))
+            .ok_or_else(|| invalid_data("event time overflow"))?;
+        events.push(RecordedEvent {
+            offset,
+            event: read_event(reader)?,
+        });
+    }
+    Ok(Recording { events })
+}
+
+fn read_event<R: Read>(reader: &mut R) -> io::Result<DeviceEvent> {
+    Ok(match read_byte(reader)? {
+        KEY_DOWN => DeviceEvent::KeyDown(read_key(reader)?),
+        KEY_UP => DeviceEvent::KeyUp(read_key(reader)?),
+        CHAR => {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            let character = char::from_u32(u32::from_le_bytes(bytes))
+                .ok_or_else(|| invalid_data("invalid character"))?;
+            DeviceEvent::Char(character)
+        }
+        MOUSE_MOVE => DeviceEvent::MouseMove((read_signed(reader)?, read_signed(reader)?)),
+        MOUSE_MOTION => DeviceEvent::MouseMotion((read_signed(reader)?, read_signed(reader)?)),
+        MOUSE_DOWN => DeviceEvent::MouseDown(read_button(reader)?),
+        MOUSE_UP => DeviceEvent::MouseUp(read_button(reader)?),
+        SCROLL => DeviceEvent::Scroll(match read_byte(reader)? {
+            0 => MouseScrollEvent::VerticalUp,
+            1 => MouseScrollEvent::VerticalDown,
+            2 => MouseScrollEvent::HorizontalRight,
+            3 => MouseScrollEvent::HorizontalLeft,
+            _ => return Err(invalid_data("invalid scroll direction")),
+        }),
+        HI_RES_SCROLL => DeviceEvent::HiResScroll(HiResScroll {
+            vertical: read_signed(reader)?,
+            horizontal: read_signed(reader)?,
+            source: match read_byte(reader)? {
+                0 => ScrollSource::Wheel,
+                1 => ScrollSource::Continuous,
+                _ => return Err(invalid_data("invalid scroll source")),
+            },
+        }),
+        _ => return Err(invalid_data("invalid event type")),
+    })
+}
+
+fn read_key<R: Read>(reader: &mut R) -> io::Result<KeyEvent> {
+    let mut name = vec![0; read_byte(reader)? as usize];
+    reader.read_exact(&mut name)?;
+    let name = String::from_utf8(name).map_err(|_| invalid_data("invalid key name"))?;
+    let key = Keycode::from_str(&name).map_err(|error| invalid_data(&error))?;
+    let mut modifiers = [0; 2];
+    reader.read_exact(&mut modifiers)?;
+    Ok(KeyEvent {
+        key,
+        modifiers: Modifiers::from_bits_retain(u16::from_le_bytes(modifiers)),
+    })
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads a byte, or returns `None` at the end of the reader.
+fn read_first_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let first = read_byte(reader)?;
+    read_varint_from(reader, first)
+}
+
+/// Reads the rest of a varint whose first byte was already read.
+fn read_varint_from<R: Read>(reader: &mut R, first: u8) -> io::Result<u64> {
+    let mut value = (first & 0x7f) as u64;
+    let mut byte = first;
+    let mut shift = 7;
+    while byte & 0x80 != 0 {
+        if shift >= 64 {
+            return Err(invalid_data("varint too long"));
+        }
+        byte = read_byte(reader)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+    }
+    Ok(value)
+}
+
+fn read_button<R: Read>(reader: &mut R) -> io::Result<MouseButton> {
+    let number =
+        usize::try_from(read_varint(reader)?).map_err(|_| invalid_data("invalid button"))?;
+    MouseButton::from_number(number).ok_or_else(|| invalid_data("invalid button"))
+}
+
+fn read_signed<R: Read>(reader: &mut R) -> io::Result<i32> {
+    let value =
+        u32::try_from(read_varint(reader)?).map_err(|_| invalid_data("invalid signed integer"))?;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
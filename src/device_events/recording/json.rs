@@ -0,0 +1,266 @@
+//! JSON lines format of recordings.
+
+use super::RecordedEvent;
+use mouse_state::{HiResScroll, MouseButton, MouseScrollEvent, ScrollSource};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+use std::time::Duration;
+use {DeviceEvent, KeyEvent, Keycode, Modifiers};
+
+pub(super) fn write_event<W: Write>(writer: &mut W, event: &RecordedEvent) -> io::Result<()> {
+    write!(writer, "{{\"time_us\":{},", event.offset.as_micros())?;
+    match event.event {
+        DeviceEvent::KeyDown(ref key) => write_key(writer, "KeyDown", key)?,
+        DeviceEvent::KeyUp(ref key) => write_key(writer, "KeyUp", key)?,
+        DeviceEvent::Char(character) => {
+            write!(writer, "\"type\":\"Char\",\"char\":")?;
+            write_string(writer, &character.to_string())?;
+        }
+        DeviceEvent::MouseMove((x, y)) => {
+            write!(writer, "\"type\":\"MouseMove\",\"x\":{},\"y\":{}", x, y)?
+        }
+        DeviceEvent::MouseMotion((dx, dy)) => write!(
+            writer,
+            "\"type\":\"MouseMotion\",\"dx\":{},\"dy\":{}",
+            dx, dy
+        )?,
+        DeviceEvent::MouseDown(button) => write!(
+            writer,
+            "\"type\":\"MouseDown\",\"button\":{}",
+            button.number()
+        )?,
+        DeviceEvent::MouseUp(button) => write!(
+            writer,
+            "\"type\":\"MouseUp\",\"button\":{}",
+            button.number()
+        )?,
+        DeviceEvent::Scroll(ref direction) => write!(
+            writer,
+            "\"type\":\"Scroll\",\"direction\":\"{:?}\"",
+            direction
+        )?,
+        DeviceEvent::HiResScroll(ref scroll) => write!(
+            writer,
+            "\"type\":\"HiResScroll\",\"vertical\":{},\"horizontal\":{},\"source\":\"{:?}\"",
+            scroll.vertical, scroll.horizontal, scroll.source
+        )?,
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_key<W: Write>(writer: &mut W, kind: &str, event: &KeyEvent) -> io::Result<()> {
+    write!(writer, "\"type\":\"{}\",\"key\":", kind)?;
+    write_string(writer, &event.key.to_string())?;
+    write!(writer, ",\"modifiers\":{}", event.modifiers.bits())
+}
+
+fn write_string<W: Write>(writer: &mut W, string: &str) -> io::Result<()> {
+    write!(writer, "\"")?;
+    for character in string.chars() {
+        match character {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            character if character.is_control() => {
+                let mut units = [0; 2];
+                for unit in character.encode_utf16(&mut units) {
+                    write!(writer, "\\u{:04x}", unit)?;
+                }
+            }
+            character => write!(writer, "{}", character)?,
+        }
+    }
+    write!(writer, "\"")
+}
+
+enum Value {
+    String(String),
+    Number(i64),
+}
+
+/// Parses an event written by [`write_event`].
+pub(super) fn parse_event(line: &str) -> Result<RecordedEvent, String> {
+    let fields = parse_object(line)?;
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("missing field {:?}", name))
+    };
+    let string = |name: &str| match field(name)? {
+        Value::String(string) => Ok(string.as_str()),
+        Value::Number(_) => Err(format!("field {:?} should be a string", name)),
+    };
+    let number = |name: &str| match field(name)? {
+        Value::Number(number) => Ok(*number),
+        Value::String(_) => Err(format!("field {:?} should be a number", name)),
+    };
+    let key = || -> Result<KeyEvent, String> {
+        let modifiers =
+            u16::try_from(number("modifiers")?).map_err(|_| "invalid modifiers".to_string())?;
+        Ok(KeyEvent {
+            key: Keycode::from_str(string("key")?)?,
+            modifiers: Modifiers::from_bits_retain(modifiers),
+        })
+    };
+    let coordinate = |name: &str| {
+        i32::try_from(number(name)?).map_err(|_| format!("invalid coordinate {:?}", name))
+    };
+    let button = || {
+        usize::try_from(number("button")?)
+            .ok()
+            .and_then(MouseButton::from_number)
+            .ok_or_else(|| "invalid button".to_string())
+    };
+
+    let offset = u64::try_from(number("time_us")?).map_err(|_| "invalid time".to_string())?;
+    let event = match string("type")? {
+        "KeyDown" => DeviceEvent::KeyDown(key()?),
+        "KeyUp" => DeviceEvent::KeyUp(key()?),
+        "Char" => {
+            let mut chars = string("char")?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(character), None) => DeviceEvent::Char(character),
+                _ => return Err("field \"char\" should be a single character".to_string()),
+            }
+        }
+        "MouseMove" => DeviceEvent::MouseMove((coordinate("x")?, coordinate("y")?)),
+        "MouseMotion" => DeviceEvent::MouseMotion((coordinate("dx")?, coordinate("dy")?)),
+        "MouseDown" => DeviceEvent::MouseDown(button()?),
+        "MouseUp" => DeviceEvent::MouseUp(button()?),
+        "Scroll" => DeviceEvent::Scroll(match string("direction")? {
+            "VerticalUp" => MouseScrollEvent::VerticalUp,
+            "VerticalDown" => MouseScrollEvent::VerticalDown,
+            "HorizontalRight" => MouseScrollEvent::HorizontalRight,
+            "HorizontalLeft" => MouseScrollEvent::HorizontalLeft,
+            direction => return Err(format!("unknown scroll direction {:?}", direction)),
+        }),
+        "HiResScroll" => DeviceEvent::HiResScroll(HiResScroll {
+            vertical: coordinate("vertical")?,
+            horizontal: coordinate("horizontal")?,
+            source: match string("source")? {
+                "Wheel" => ScrollSource::Wheel,
+                "Continuous" => ScrollSource::Continuous,
+                source => return Err(format!("unknown scroll source {:?}", source)),
+            },
+        }),
+        kind => return Err(format!("unknown event type {:?}", kind)),
+    };
+    Ok(RecordedEvent {
+        offset: Duration::from_micros(offset),
+        event,
+    })
+}
+
+/// Parses a JSON object whose values are strings or integers.
+fn parse_object(line: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut chars = line.chars().peekable();
+    let mut fields = vec![];
+    skip_whitespace(&mut chars);
+    expect(&mut chars, '{')?;
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = parse_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            expect(&mut chars, ':')?;
+            skip_whitespace(&mut chars);
+            let value = if chars.peek() == Some(&'"') {
+                Value::String(parse_string(&mut chars)?)
+            } else {
+                Value::Number(parse_number(&mut chars)?)
+            };
+            fields.push((key, value));
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected ',' or '}'".to_string()),
+            }
+        }
+    }
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(fields),
+        Some(_) => Err("unexpected characters after the object".to_string()),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars<'_>>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        _ => Err(format!("expected {:?}", expected)),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('/') => string.push('/'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('u') => {
+                    let unit = parse_unicode_escape(chars)?;
+                    let mut units = vec![unit];
+                    // Characters outside the BMP are escaped as surrogate pairs.
+                    if (0xd800..0xdc00).contains(&unit) {
+                        expect(chars, '\\')?;
+                        expect(chars, 'u')?;
+                        units.push(parse_unicode_escape(chars)?);
+                    }
+                    let character = char::decode_utf16(units)
+                        .next()
+                        .and_then(Result::ok)
+                        .ok_or_else(|| "invalid unicode escape".to_string())?;
+                    string.push(character);
+                }
+                _ => return Err("invalid escape".to_string()),
+            },
+            Some(c) => string.push(c),
+        }
+    }
+}
+
+fn parse_unicode_escape(chars: &mut Peekable<Chars<'_>>) -> Result<u16, String> {
+    let digits: String = chars.take(4).collect();
+    u16::from_str_radix(&digits, 16).map_err(|_| "invalid unicode escape".to_string())
+}
+
+fn parse_number(chars: &mut Peekable<Chars<'_>>) -> Result<i64, String> {
+    let mut number = String::new();
+    if chars.peek() == Some(&'-') {
+        number.push('-');
+        chars.next();
+    }
+    while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        number.push(*digit);
+        chars.next();
+    }
+    number
+        .parse()
+        .map_err(|_| "expected a string or an integer".to_string())
+}
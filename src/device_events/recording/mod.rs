@@ -0,0 +1,241 @@
+//! Recording and replay of input sessions.
+//!
+//! A [`Recorder`] captures the events of a [`DeviceEventsHandler`] into a [`Recording`],
+//! which can be saved in one of two formats and replayed later by a [`Player`].
+
+mod binary;
+mod json;
+
+use super::{DeviceEvent, DeviceEventsHandler, TimedEvent};
+use std::io::{self, BufRead, Read, Write};
+use std::sync::mpsc::Receiver;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// An event of a [`Recording`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// The time elapsed between the start of the recording and the event.
+    pub offset: Duration,
+    /// The event.
+    pub event: DeviceEvent,
+}
+
+/// A recorded input session, which can be saved in two file formats.
+///
+/// # JSON lines format
+///
+/// One JSON object per line and per event. `time_us` is the number of microseconds since
+/// the recording started, and `type` the [`DeviceEvent`] variant, which sets the other fields:
+///
+/// ```text
+/// {"time_us":0,"type":"KeyDown","key":"LShift","modifiers":1}
+/// {"time_us":80512,"type":"Char","char":"A"}
+/// {"time_us":153000,"type":"MouseMove","x":640,"y":480}
+/// {"time_us":153000,"type":"MouseMotion","dx":-2,"dy":5}
+/// {"time_us":201337,"type":"MouseDown","button":1}
+/// {"time_us":290001,"type":"Scroll","direction":"VerticalUp"}
+/// {"time_us":290001,"type":"HiResScroll","vertical":120,"horizontal":0,"source":"Wheel"}
+/// ```
+///
+/// `key` is the [`Keycode`](crate::Keycode) name as printed by `Display`, `modifiers` the
+/// bits of the [`Modifiers`](crate::Modifiers) flags, `button` the
+/// [`MouseButton::number`](crate::MouseButton::number), and `direction` the
+/// [`MouseScrollEvent`](crate::MouseScrollEvent) variant. `vertical` and `horizontal` are in
+/// 1/120 of a notch, and `source` the [`ScrollSource`](crate::ScrollSource) variant.
+///
+/// # Binary format
+///
+/// The bytes `DQREC` and the format version, `1`, followed by the events. Unsigned
+/// integers are LEB128 varints, signed ones are zigzag encoded first. Each event is:
+///
+/// * the microseconds elapsed since the previous event, or since the start for the first one,
+/// * its type: 0 `KeyDown`, 1 `KeyUp`, 2 `Char`, 3 `MouseMove`, 4 `MouseDown`, 5 `MouseUp`, 6 `Scroll`,
+///   7 `MouseMotion`, 8 `HiResScroll`,
+/// * for key events, the key name length as one byte, the key name and the modifier bits
+///   as a little endian `u16`,
+/// * for `Char`, the character as a little endian `u32`,
+/// * for `MouseMove`, the signed `x` and `y` coordinates, and for `MouseMotion`, the signed
+///   `dx` and `dy` deltas,
+/// * for button events, the [`MouseButton::number`](crate::MouseButton::number),
+/// * for `Scroll`, one byte: 0 up, 1 down, 2 right, 3 left,
+/// * for `HiResScroll`, the signed `vertical` and `horizontal` movements, then one byte for
+///   the source: 0 wheel, 1 continuous.
+///
+/// # Example
+///
+/// ```
+/// use device_query::{DeviceEvent, HiResScroll, KeyEvent, Keycode, Modifiers, RecordedEvent, Recording, ScrollSource};
+/// use std::time::Duration;
+///
+/// let recording = Recording {
+///     events: vec![
+///         RecordedEvent {
+///             offset: Duration::from_millis(20),
+///             event: DeviceEvent::KeyDown(KeyEvent { key: Keycode::Q, modifiers: Modifiers::LSHIFT }),
+///         },
+///         RecordedEvent { offset: Duration::from_millis(25), event: DeviceEvent::Char('Q') },
+///         RecordedEvent { offset: Duration::from_millis(90), event: DeviceEvent::MouseMove((-3, 7)) },
+///         RecordedEvent { offset: Duration::from_millis(95), event: DeviceEvent::MouseMotion((4, -1)) },
+///         RecordedEvent {
+///             offset: Duration::from_millis(120),
+///             event: DeviceEvent::HiResScroll(HiResScroll { vertical: -45, horizontal: 3, source: ScrollSource::Continuous }),
+///         },
+///     ],
+/// };
+///
+/// let mut json = vec![];
+/// recording.write_json(&mut json).unwrap();
+/// assert_eq!(Recording::read_json(&json[..]).unwrap(), recording);
+///
+/// let mut binary = vec![];
+/// recording.write_binary(&mut binary).unwrap();
+/// assert_eq!(Recording::read_binary(&binary[..]).unwrap(), recording);
+/// assert!(binary.len() < json.len());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Recording {
+    /// The recorded events, in the order they happened.
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Writes the recording in the JSON lines format.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for event in &self.events {
+            json::write_event(&mut writer, event)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a recording in the JSON lines format. Empty lines are skipped.
+    pub fn read_json<R: BufRead>(reader: R) -> io::Result<Recording> {
+        let mut events = vec![];
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = json::parse_event(&line).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", index + 1, error),
+                )
+            })?;
+            events.push(event);
+        }
+        Ok(Recording { events })
+    }
+
+    /// Writes the recording in the binary format.
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        binary::write_recording(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Reads a recording in the binary format. The reader is read one byte at a time, so
+    /// files should be wrapped in a [`BufReader`](std::io::BufReader).
+    pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Recording> {
+        binary::read_recording(&mut reader)
+    }
+}
+
+/// Records the events of a [`DeviceEventsHandler`] until it is stopped.
+///
+/// ```no_run
+/// use device_query::{DeviceEventsHandler, Recorder};
+/// use std::fs::File;
+/// use std::thread::sleep;
+/// use std::time::Duration;
+///
+/// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+/// let recorder = Recorder::start(&device_events);
+/// sleep(Duration::from_secs(10));
+/// let recording = recorder.stop();
+/// recording.write_json(File::create("session.jsonl").unwrap()).unwrap();
+/// ```
+pub struct Recorder {
+    receiver: Receiver<TimedEvent>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Starts recording the events of the handler.
+    pub fn start(handler: &DeviceEventsHandler) -> Recorder {
+        Recorder {
+            receiver: handler.subscribe_timed(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Stops recording and returns the events recorded so far.
+    pub fn stop(self) -> Recording {
+        let events = self
+            .receiver
+            .try_iter()
+            .map(|event| RecordedEvent {
+                offset: event.time.saturating_duration_since(self.start),
+                event: event.event,
+            })
+            .collect();
+        Recording { events }
+    }
+}
+
+/// Replays a [`Recording`] to the callbacks and subscribers of a [`DeviceEventsHandler`],
+/// as if the events were happening again.
+///
+/// ```no_run
+/// use device_query::{DeviceEvents, DeviceEventsHandler, Player, Recording};
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use std::time::Duration;
+///
+/// let device_events = DeviceEventsHandler::new(Duration::from_millis(10)).unwrap();
+/// let _guard = device_events.on_key_down(|key| println!("Key down: {:?}", key));
+///
+/// let file = BufReader::new(File::open("session.jsonl").unwrap());
+/// let player = Player::new(Recording::read_json(file).unwrap()).with_speed(2.0);
+/// player.play(&device_events);
+/// ```
+pub struct Player {
+    recording: Recording,
+    speed: f64,
+}
+
+impl Player {
+    /// Creates a player replaying the recording at its original speed.
+    pub fn new(recording: Recording) -> Player {
+        Player {
+            recording,
+            speed: 1.0,
+        }
+    }
+
+    /// Scales the replay speed: 2.0 replays twice as fast, 0.5 twice as slow.
+    ///
+    /// A speed that isn't positive, including NaN, is clamped to the smallest positive
+    /// one, which all but pauses the replay.
+    pub fn with_speed(mut self, speed: f64) -> Player {
+        self.speed = speed.max(f64::MIN_POSITIVE);
+        self
+    }
+
+    /// Replays the recording, blocking until its last event. Each event is dispatched
+    /// with the time it is replayed. Stops early if the handler is shut down.
+    pub fn play(&self, handler: &DeviceEventsHandler) {
+        let start = Instant::now();
+        for event in &self.recording.events {
+            // Saturates rather than overflowing when replaying very slowly.
+            let delay = Duration::try_from_secs_f64(event.offset.as_secs_f64() / self.speed)
+                .unwrap_or(Duration::MAX);
+            let elapsed = start.elapsed();
+            if delay > elapsed {
+                sleep(delay - elapsed);
+            }
+            if !handler.dispatch(event.event.clone(), Instant::now()) {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Key sequences, such as `g g` or `Ctrl+X Ctrl+S`.
+
+use super::KeyEvent;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use {Hotkey, Modifiers};
+
+/// Hotkeys to press one after the other, such as vim's `g g` or Emacs' `Ctrl+X Ctrl+S`.
+///
+/// It is parsed from the accelerator strings of its steps separated by spaces, see [`Hotkey`].
+///
+/// ```
+/// use device_query::{Hotkey, KeySequence, Keycode, Modifiers};
+///
+/// let sequence: KeySequence = "Ctrl+X Ctrl+S".parse().unwrap();
+/// assert_eq!(sequence.steps()[1], Hotkey::new(Modifiers::CONTROL, Keycode::S));
+/// assert_eq!(sequence.to_string(), "Ctrl+X Ctrl+S");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence {
+    steps: Vec<Hotkey>,
+}
+
+impl KeySequence {
+    /// Creates a sequence from its steps.
+    pub fn new(steps: Vec<Hotkey>) -> KeySequence {
+        KeySequence { steps }
+    }
+
+    /// The hotkeys to press, in order.
+    pub fn steps(&self) -> &[Hotkey] {
+        &self.steps
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split_whitespace()
+            .map(Hotkey::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err("empty key sequence".to_string());
+        }
+        Ok(KeySequence { steps })
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of feeding a key press to a [`SequenceMatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceMatch<'a, T> {
+    /// The key press doesn't continue nor start any sequence.
+    NoMatch,
+    /// The key press continues at least one sequence, with this number of steps matched.
+    Partial(usize),
+    /// The key press completes the sequence registered with this value.
+    Complete(&'a T),
+}
+
+/// A node of the sequence trie.
+#[derive(Debug)]
+struct Node<T> {
+    children: Vec<(Hotkey, Node<T>)>,
+    value: Option<T>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Node {
+            children: vec![],
+            value: None,
+        }
+    }
+}
+
+impl<T> Node<T> {
+    fn descend(&self, path: &[usize]) -> &Node<T> {
+        path.iter()
+            .fold(self, |node, &index| &node.children[index].1)
+    }
+
+    fn find_mut(&mut self, steps: &[Hotkey]) -> Option<&mut Node<T>> {
+        match steps.split_first() {
+            None => Some(self),
+            Some((step, steps)) => self
+                .children
+                .iter_mut()
+                .find(|(hotkey, _)| hotkey == step)
+                .and_then(|(_, child)| child.find_mut(steps)),
+        }
+    }
+
+    fn remove(&mut self, steps: &[Hotkey]) -> Option<T> {
+        let (step, steps) = match steps.split_first() {
+            None => return self.value.take(),
+            Some(split) => split,
+        };
+        let index = self
+            .children
+            .iter()
+            .position(|(hotkey, _)| hotkey == step)?;
+        let value = self.children[index].1.remove(steps);
+        if self.children[index].1.is_empty() {
+            self.children.remove(index);
+        }
+        value
+    }
+
+    /// Removes the values rejected by `keep`. Returns true if any was removed.
+    fn retain<F: FnMut(&mut T) -> bool>(&mut self, keep: &mut F) -> bool {
+        let mut removed = false;
+        if let Some(ref mut value) = self.value {
+            if !keep(value) {
+                self.value = None;
+                removed = true;
+            }
+        }
+        for (_, child) in self.children.iter_mut() {
+            removed |= child.retain(keep);
+        }
+        self.children.retain(|(_, child)| !child.is_empty());
+        removed
+    }
+
+    fn is_empty(&self) -> bool {
+        self.value.is_none() && self.children.is_empty()
+    }
+}
+
+/// Detects key sequences in a stream of key presses.
+///
+/// The sequences are stored in a trie, so that sequences sharing a prefix are followed
+/// at the same time. The progress is reset when a key press matches none of them, or when
+/// more than the timeout elapsed since the previous step. Modifier keys pressed in the
+/// middle of a sequence don't break it.
+///
+/// When a sequence is a prefix of another one, such as `g` and `g g`, both complete.
+///
+/// ```
+/// use device_query::{KeyEvent, Keycode, Modifiers, SequenceMatch, SequenceMatcher};
+/// use std::time::Instant;
+///
+/// let mut matcher = SequenceMatcher::default();
+/// matcher.insert("g g".parse().unwrap(), "top");
+/// matcher.insert("g e".parse().unwrap(), "end");
+///
+/// let press = |key| KeyEvent { key, modifiers: Modifiers::empty() };
+/// let now = Instant::now();
+/// assert_eq!(matcher.feed(&press(Keycode::G), now), SequenceMatch::Partial(1));
+/// assert_eq!(matcher.feed(&press(Keycode::X), now), SequenceMatch::NoMatch);
+/// assert_eq!(matcher.feed(&press(Keycode::G), now), SequenceMatch::Partial(1));
+/// assert_eq!(matcher.feed(&press(Keycode::E), now), SequenceMatch::Complete(&"end"));
+/// ```
+#[derive(Debug)]
+pub struct SequenceMatcher<T> {
+    root: Node<T>,
+    timeout: Duration,
+    /// The children followed from the root by the steps matched so far.
+    path: Vec<usize>,
+    last_step: Option<Instant>,
+}
+
+impl<T> Default for SequenceMatcher<T> {
+    fn default() -> Self {
+        SequenceMatcher::new(Self::DEFAULT_TIMEOUT)
+    }
+}
+
+impl<T> SequenceMatcher<T> {
+    /// The longest time between two steps of a sequence, unless given otherwise.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Creates a matcher without any sequence, allowing up to `timeout` between two steps.
+    pub fn new(timeout: Duration) -> SequenceMatcher<T> {
+        SequenceMatcher {
+            root: Node::default(),
+            timeout,
+            path: vec![],
+            last_step: None,
+        }
+    }
+
+    /// Registers a sequence along with the value returned when it completes. Returns the
+    /// value it was registered with before, if any.
+    pub fn insert(&mut self, sequence: KeySequence, value: T) -> Option<T> {
+        self.reset();
+        let mut node = &mut self.root;
+        for step in sequence.steps {
+            let index = match node.children.iter().position(|(hotkey, _)| *hotkey == step) {
+                Some(index) => index,
+                None => {
+                    node.children.push((step, Node::default()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[index].1;
+        }
+        node.value.replace(value)
+    }
+
+    /// Returns the value of a registered sequence.
+    pub fn get_mut(&mut self, sequence: &KeySequence) -> Option<&mut T> {
+        self.root
+            .find_mut(&sequence.steps)
+            .and_then(|node| node.value.as_mut())
+    }
+
+    /// Unregisters a sequence, returning its value.
+    pub fn remove(&mut self, sequence: &KeySequence) -> Option<T> {
+        self.reset();
+        self.root.remove(&sequence.steps)
+    }
+
+    /// Unregisters the sequences whose value is rejected by `keep`.
+    pub fn retain<F: FnMut(&mut T) -> bool>(&mut self, mut keep: F) {
+        if self.root.retain(&mut keep) {
+            self.reset();
+        }
+    }
+
+    /// Returns the number of steps matched so far.
+    pub fn progress(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Forgets the steps matched so far.
+    pub fn reset(&mut self) {
+        self.path.clear();
+        self.last_step = None;
+    }
+
+    /// Advances the sequences with a key press, which happened at `time`.
+    ///
+    /// The timeout is measured between the times of the key presses, such as
+    /// [`TimedEvent::time`](crate::TimedEvent::time), so that a delay in delivering or
+    /// replaying them doesn't break a sequence.
+    ///
+    /// ```
+    /// use device_query::{KeyEvent, Keycode, Modifiers, SequenceMatch, SequenceMatcher};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut matcher = SequenceMatcher::new(Duration::from_millis(50));
+    /// matcher.insert("Ctrl+X Ctrl+S".parse().unwrap(), ());
+    ///
+    /// let ctrl = |key| KeyEvent { key, modifiers: Modifiers::LCONTROL };
+    /// let start = Instant::now();
+    /// assert_eq!(matcher.feed(&ctrl(Keycode::X), start), SequenceMatch::Partial(1));
+    /// let late = start + Duration::from_millis(100);
+    /// assert_eq!(matcher.feed(&ctrl(Keycode::S), late), SequenceMatch::NoMatch);
+    /// assert_eq!(matcher.progress(), 0);
+    /// ```
+    pub fn feed(&mut self, event: &KeyEvent, time: Instant) -> SequenceMatch<'_, T> {
+        if self
+            .last_step
+            .is_some_and(|last_step| time.saturating_duration_since(last_step) > self.timeout)
+        {
+            self.reset();
+        }
+        let node = self.root.descend(&self.path);
+        let index = match node
+            .children
+            .iter()
+            .position(|(hotkey, _)| hotkey.matches(event))
+        {
+            Some(index) => index,
+            None if self.path.is_empty() => return SequenceMatch::NoMatch,
+            None if !Modifiers::from_key(event.key).is_empty() => {
+                return SequenceMatch::Partial(self.path.len())
+            }
+            // The key press may start another sequence.
+            None => {
+                self.reset();
+                return self.feed(event, time);
+            }
+        };
+        self.path.push(index);
+        self.last_step = Some(time);
+        let steps = self.path.len();
+        let node = self.root.descend(&self.path);
+        if node.children.is_empty() {
+            self.path.clear();
+            self.last_step = None;
+        }
+        match node.value {
+            Some(ref value) => SequenceMatch::Complete(value),
+            None => SequenceMatch::Partial(steps),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Asynchronous stream of device events.
+
+use super::{DeviceEvent, EventSubscriber, TimedEvent};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Default)]
+struct StreamState {
+    queue: VecDeque<DeviceEvent>,
+    dropped: u64,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+struct StreamShared {
+    capacity: usize,
+    state: Mutex<StreamState>,
+}
+
+/// A [`Stream`] of every [`DeviceEvent`], in the order they were detected.
+///
+/// Events are buffered until the stream is polled. When the consumer is too slow and
+/// the buffer is full, the oldest event is dropped to make room for the new one; the
+/// number of events lost this way is available through [`dropped`](Self::dropped).
+/// The stream ends when the event loop stops.
+///
+/// Created by [`DeviceEventsHandler::subscribe_stream`](crate::DeviceEventsHandler::subscribe_stream).
+pub struct DeviceEventStream {
+    shared: Arc<StreamShared>,
+}
+
+impl DeviceEventStream {
+    pub(crate) fn new(capacity: usize) -> (StreamSubscriber, DeviceEventStream) {
+        let shared = Arc::new(StreamShared {
+            capacity: capacity.max(1),
+            state: Mutex::new(StreamState::default()),
+        });
+        let subscriber = StreamSubscriber {
+            shared: Arc::downgrade(&shared),
+        };
+        (subscriber, DeviceEventStream { shared })
+    }
+
+    /// Number of events dropped because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared
+            .state
+            .lock()
+            .map(|state| state.dropped)
+            .unwrap_or_default()
+    }
+}
+
+impl Stream for DeviceEventStream {
+    type Item = DeviceEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DeviceEvent>> {
+        let Ok(mut state) = self.shared.state.lock() else {
+            return Poll::Ready(None);
+        };
+        if let Some(event) = state.queue.pop_front() {
+            Poll::Ready(Some(event))
+        } else if state.closed {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// The event loop end of a [`DeviceEventStream`].
+pub(crate) struct StreamSubscriber {
+    shared: Weak<StreamShared>,
+}
+
+impl EventSubscriber for StreamSubscriber {
+    fn send(&self, event: &TimedEvent) -> bool {
+        let Some(shared) = self.shared.upgrade() else {
+            return false;
+        };
+        let Ok(mut state) = shared.state.lock() else {
+            return false;
+        };
+        if state.queue.len() >= shared.capacity {
+            state.queue.pop_front();
+            state.dropped += 1;
+        }
+        state.queue.push_back(event.event.clone());
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+}
+
+impl Drop for StreamSubscriber {
+    /// Ends the stream once the event loop is gone.
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            if let Ok(mut state) = shared.state.lock() {
+                state.closed = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! Query functions.
 
 use DeviceState;
-use {Keycode, MouseState};
+use {Error, Keycode, Modifiers, Monitor, MouseState};
 
 /// Trait to get the state of the supported devices.
 pub trait DeviceQuery {
@@ -10,6 +10,36 @@ pub trait DeviceQuery {
 
     /// Get Keyboard state.
     fn get_keys(&self) -> Vec<Keycode>;
+
+    /// Same as [`get_mouse`](Self::get_mouse), failing when the devices can't be read,
+    /// for instance because one of them was unplugged. The polling event loop reports
+    /// these errors to [`DeviceEvents::on_error`](crate::DeviceEvents::on_error).
+    fn try_get_mouse(&self) -> Result<MouseState, Error> {
+        Ok(self.get_mouse())
+    }
+
+    /// Same as [`get_keys`](Self::get_keys), failing when the devices can't be read.
+    fn try_get_keys(&self) -> Result<Vec<Keycode>, Error> {
+        Ok(self.get_keys())
+    }
+
+    /// Get the modifiers held, and the lock keys toggled on when the platform tells them.
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers::from_keys(&self.get_keys())
+    }
+
+    /// Get the character typed by the key in the current keyboard layout, taking the
+    /// modifiers currently held into account. Returns `None` when the key doesn't type
+    /// a printable character or the layout is unknown.
+    fn get_char(&self, _keycode: Keycode) -> Option<char> {
+        None
+    }
+
+    /// Get the monitors, in the order [`MouseState::monitor`] indexes them. Empty when
+    /// the device has no notion of monitors.
+    fn get_monitors(&self) -> Vec<Monitor> {
+        vec![]
+    }
 }
 
 impl DeviceQuery for DeviceState {
@@ -22,4 +52,27 @@ impl DeviceQuery for DeviceState {
     fn get_keys(&self) -> Vec<Keycode> {
         self.query_keymap()
     }
+
+    fn try_get_mouse(&self) -> Result<MouseState, Error> {
+        self.try_query_pointer()
+    }
+
+    fn try_get_keys(&self) -> Result<Vec<Keycode>, Error> {
+        self.try_query_keymap()
+    }
+
+    /// Query for the modifiers held and the lock keys toggled on.
+    fn get_modifiers(&self) -> Modifiers {
+        self.query_modifiers()
+    }
+
+    /// Translate the key using the active keyboard layout (XKB on Linux).
+    fn get_char(&self, keycode: Keycode) -> Option<char> {
+        self.query_char(keycode)
+    }
+
+    /// Query the monitors (XRandR on Linux).
+    fn get_monitors(&self) -> Vec<Monitor> {
+        self.query_monitors()
+    }
 }
@@ -0,0 +1,549 @@
+//! Evdev backend reading `/dev/input/event*` directly, without a display server.
+
+extern crate libc;
+
+use error::Error;
+use modifiers::Modifiers;
+use mouse_state::{HiResScroll, MouseDelta, MousePosition, ScrollDelta};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::kernel_key;
+use super::xkb::XkbKeymap;
+
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const EV_LED: u16 = 0x11;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const REL_HWHEEL_HI_RES: u16 = 0x0c;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+
+const BTN_TOUCH: u16 = 0x14a;
+
+/// The device is a touchpad moving the cursor relatively to where the finger landed,
+/// rather than a tablet or a touchscreen mapped onto the screen.
+const INPUT_PROP_POINTER: usize = 0x00;
+
+const LED_NUML: usize = 0x00;
+const LED_CAPSL: usize = 0x01;
+const LED_SCROLLL: usize = 0x02;
+
+/// Size in bytes of a bitmask holding every key and button code.
+const KEY_BITS_LEN: usize = (kernel_key::KEY_MAX as usize) / 8 + 1;
+
+/// How often `/dev/input` is scanned for the devices plugged since.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The size of the screen of the console, onto which absolute devices are mapped.
+const FRAMEBUFFER_SIZE: &str = "/sys/class/graphics/fb0/virtual_size";
+
+/// Equivalent of the kernel `_IOC(_IOC_READ, 'E', nr, size)` macro.
+fn eviocread(nr: u32, size: usize) -> libc::c_ulong {
+    const IOC_READ: libc::c_ulong = 2;
+    (IOC_READ << 30)
+        | ((size as libc::c_ulong) << 16)
+        | ((b'E' as libc::c_ulong) << 8)
+        | nr as libc::c_ulong
+}
+
+/// `EVIOCGBIT(ev, len)`: the event types (`ev == 0`) or codes supported by a device.
+fn eviocgbit(ev: u16, len: usize) -> libc::c_ulong {
+    eviocread(0x20 + ev as u32, len)
+}
+
+/// `EVIOCGKEY(len)`: the current state of every key and button of a device.
+fn eviocgkey(len: usize) -> libc::c_ulong {
+    eviocread(0x18, len)
+}
+
+/// `EVIOCGLED(len)`: the current state of every LED of a device.
+fn eviocgled(len: usize) -> libc::c_ulong {
+    eviocread(0x19, len)
+}
+
+/// `EVIOCGPROP(len)`: the properties of a device, such as `INPUT_PROP_POINTER`.
+fn eviocgprop(len: usize) -> libc::c_ulong {
+    eviocread(0x09, len)
+}
+
+/// `EVIOCGABS(abs)`: the value and range of an absolute axis.
+fn eviocgabs(abs: u16) -> libc::c_ulong {
+    eviocread(0x40 + abs as u32, mem::size_of::<libc::input_absinfo>())
+}
+
+fn test_bit(bits: &[u8], bit: usize) -> bool {
+    bits.get(bit / 8)
+        .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// The range of an absolute axis.
+#[derive(Debug, Clone, Copy)]
+struct AbsAxis {
+    minimum: i32,
+    maximum: i32,
+}
+
+impl AbsAxis {
+    fn read(file: &File, abs: u16) -> Option<AbsAxis> {
+        let mut info: libc::input_absinfo = unsafe { mem::zeroed() };
+        let res = unsafe { libc::ioctl(file.as_raw_fd(), eviocgabs(abs) as _, &mut info) };
+        if res < 0 || info.maximum <= info.minimum {
+            return None;
+        }
+        Some(AbsAxis {
+            minimum: info.minimum,
+            maximum: info.maximum,
+        })
+    }
+
+    /// Maps a value of the axis onto `0..size`, or makes it start from 0 without size.
+    fn scale(&self, value: i32, size: Option<i32>) -> i32 {
+        let offset = i64::from(value.clamp(self.minimum, self.maximum) - self.minimum);
+        match size {
+            Some(size) => {
+                let range = i64::from(self.maximum) - i64::from(self.minimum);
+                (offset * i64::from(size - 1) / range) as i32
+            }
+            None => offset as i32,
+        }
+    }
+}
+
+/// The X and Y axes of an absolute pointing device.
+#[derive(Debug)]
+struct AbsPointer {
+    x: AbsAxis,
+    y: AbsAxis,
+    /// Whether this is a touchpad, whose axes move the pointer by the difference between
+    /// two positions of the finger.
+    touchpad: bool,
+    /// The last position of the finger on a touchpad, unknown until it touches.
+    touch: (Option<i32>, Option<i32>),
+}
+
+#[derive(Debug)]
+struct InputDevice {
+    path: PathBuf,
+    file: File,
+    has_keys: bool,
+    has_leds: bool,
+    /// Whether the device reports `REL_WHEEL_HI_RES` along with `REL_WHEEL`.
+    has_hi_res_wheel: bool,
+    /// Whether the device reports `REL_HWHEEL_HI_RES` along with `REL_HWHEEL`.
+    has_hi_res_hwheel: bool,
+    abs_pointer: Option<AbsPointer>,
+}
+
+impl InputDevice {
+    fn open(path: &Path) -> io::Result<InputDevice> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open(path)?;
+        let mut ev_bits = [0u8; 4];
+        let res = unsafe {
+            libc::ioctl(
+                file.as_raw_fd(),
+                eviocgbit(0, ev_bits.len()) as _,
+                ev_bits.as_mut_ptr(),
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut rel_bits = [0u8; 2];
+        if test_bit(&ev_bits, EV_REL as usize) {
+            unsafe {
+                libc::ioctl(
+                    file.as_raw_fd(),
+                    eviocgbit(EV_REL, rel_bits.len()) as _,
+                    rel_bits.as_mut_ptr(),
+                );
+            }
+        }
+        let abs_pointer = if test_bit(&ev_bits, EV_ABS as usize) {
+            Self::abs_pointer(&file)
+        } else {
+            None
+        };
+        Ok(InputDevice {
+            path: path.to_path_buf(),
+            file,
+            has_keys: test_bit(&ev_bits, EV_KEY as usize),
+            has_leds: test_bit(&ev_bits, EV_LED as usize),
+            has_hi_res_wheel: test_bit(&rel_bits, REL_WHEEL_HI_RES as usize),
+            has_hi_res_hwheel: test_bit(&rel_bits, REL_HWHEEL_HI_RES as usize),
+            abs_pointer,
+        })
+    }
+
+    /// Returns the X and Y axes of the device, if it has both.
+    fn abs_pointer(file: &File) -> Option<AbsPointer> {
+        let x = AbsAxis::read(file, ABS_X)?;
+        let y = AbsAxis::read(file, ABS_Y)?;
+        let mut props = [0u8; 4];
+        unsafe {
+            libc::ioctl(
+                file.as_raw_fd(),
+                eviocgprop(props.len()) as _,
+                props.as_mut_ptr(),
+            );
+        }
+        Some(AbsPointer {
+            x,
+            y,
+            touchpad: test_bit(&props, INPUT_PROP_POINTER),
+            touch: (None, None),
+        })
+    }
+
+    /// ORs the pressed keys and buttons of this device into `bits`. Fails once the
+    /// device is unplugged.
+    fn read_key_bits(&self, bits: &mut [u8; KEY_BITS_LEN]) -> io::Result<()> {
+        if !self.has_keys {
+            return Ok(());
+        }
+        let mut device_bits = [0u8; KEY_BITS_LEN];
+        let res = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                eviocgkey(device_bits.len()) as _,
+                device_bits.as_mut_ptr(),
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        for (bit, device_bit) in bits.iter_mut().zip(device_bits.iter()) {
+            *bit |= device_bit;
+        }
+        Ok(())
+    }
+
+    /// Returns the lock keys toggled on according to the LEDs of this device.
+    fn lock_modifiers(&self) -> Modifiers {
+        let mut leds = [0u8; 1];
+        if !self.has_leds {
+            return Modifiers::empty();
+        }
+        let res = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                eviocgled(leds.len()) as _,
+                leds.as_mut_ptr(),
+            )
+        };
+        let mut modifiers = Modifiers::empty();
+        if res < 0 {
+            return modifiers;
+        }
+        modifiers.set(Modifiers::NUM_LOCK, test_bit(&leds, LED_NUML));
+        modifiers.set(Modifiers::CAPS_LOCK, test_bit(&leds, LED_CAPSL));
+        modifiers.set(Modifiers::SCROLL_LOCK, test_bit(&leds, LED_SCROLLL));
+        modifiers
+    }
+
+    /// Reads all the pending events of this device without blocking. Fails once the
+    /// device is unplugged.
+    fn read_events(&self, events: &mut Vec<libc::input_event>) -> io::Result<()> {
+        let mut buffer: [libc::input_event; 64] = unsafe { mem::zeroed() };
+        loop {
+            let read = unsafe {
+                libc::read(
+                    self.file.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    mem::size_of_val(&buffer),
+                )
+            };
+            if read < 0 {
+                let error = io::Error::last_os_error();
+                return match error.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => Ok(()),
+                    _ => Err(error),
+                };
+            }
+            if read == 0 {
+                return Ok(());
+            }
+            let count = read as usize / mem::size_of::<libc::input_event>();
+            events.extend_from_slice(&buffer[..count]);
+        }
+    }
+}
+
+/// The devices currently open.
+#[derive(Debug)]
+struct Devices {
+    list: Vec<InputDevice>,
+    /// When `/dev/input` was last scanned, `None` to scan it again right away.
+    scanned: Option<Instant>,
+    /// Whether a device was unplugged since the last call to `EvdevDevices::take_lost`.
+    lost: bool,
+}
+
+impl Devices {
+    /// Opens the devices of `/dev/input` not opened yet. Returns whether some of them
+    /// couldn't be opened because of their permissions.
+    fn scan(&mut self) -> io::Result<bool> {
+        self.scanned = Some(Instant::now());
+        let mut permission_denied = false;
+        for entry in fs::read_dir("/dev/input")?.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if !entry.file_name().to_string_lossy().starts_with("event")
+                || self.list.iter().any(|device| device.path == path)
+            {
+                continue;
+            }
+            match InputDevice::open(&path) {
+                Ok(device) => self.list.push(device),
+                Err(error) => permission_denied |= error.kind() == io::ErrorKind::PermissionDenied,
+            }
+        }
+        Ok(permission_denied)
+    }
+
+    /// Closes the devices that failed, which were unplugged, to scan again for those
+    /// plugged back.
+    fn remove(&mut self, failed: &[PathBuf]) {
+        if failed.is_empty() {
+            return;
+        }
+        self.list.retain(|device| !failed.contains(&device.path));
+        self.lost = true;
+        self.scanned = None;
+    }
+}
+
+#[derive(Debug, Default)]
+struct PointerState {
+    coords: MousePosition,
+    motion_delta: MouseDelta,
+    scroll_delta: ScrollDelta,
+    hi_res_scroll: HiResScroll,
+}
+
+/// Every input device readable from `/dev/input`.
+#[derive(Debug)]
+pub(super) struct EvdevDevices {
+    devices: Mutex<Devices>,
+    pointer: Mutex<PointerState>,
+    keymap: Option<XkbKeymap>,
+    /// The size of the console screen, when there is a framebuffer.
+    screen_size: Option<(i32, i32)>,
+}
+
+impl EvdevDevices {
+    /// Opens every `/dev/input/event*` device we are allowed to read.
+    /// Fails if none of them could be opened.
+    pub fn open() -> Result<EvdevDevices, Error> {
+        let mut devices = Devices {
+            list: vec![],
+            scanned: None,
+            lost: false,
+        };
+        let permission_denied = devices
+            .scan()
+            .map_err(|error| Error::BackendUnavailable(format!("/dev/input: {}", error)))?;
+        if devices.list.is_empty() {
+            return Err(if permission_denied {
+                Error::PermissionDenied
+            } else {
+                Error::BackendUnavailable("no input device in /dev/input".to_string())
+            });
+        }
+        Ok(EvdevDevices {
+            devices: Mutex::new(devices),
+            pointer: Mutex::new(PointerState::default()),
+            keymap: XkbKeymap::new(),
+            screen_size: Self::framebuffer_size(),
+        })
+    }
+
+    fn framebuffer_size() -> Option<(i32, i32)> {
+        let size = fs::read_to_string(FRAMEBUFFER_SIZE).ok()?;
+        let (width, height) = size.trim().split_once(',')?;
+        let size = (width.parse().ok()?, height.parse().ok()?);
+        (size.0 > 0 && size.1 > 0).then_some(size)
+    }
+
+    /// Returns the open devices, after opening those plugged since the last scan.
+    fn devices(&self) -> MutexGuard<'_, Devices> {
+        let mut devices = match self.devices.lock() {
+            Ok(devices) => devices,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let stale = devices
+            .scanned
+            .is_none_or(|scanned| scanned.elapsed() >= RESCAN_INTERVAL);
+        if stale {
+            let _ = devices.scan();
+        }
+        devices
+    }
+
+    /// Returns whether a device was unplugged since the last call.
+    pub fn take_lost(&self) -> bool {
+        mem::take(&mut self.devices().lost)
+    }
+
+    /// Returns the pressed state of every key and button code, merged over all devices.
+    pub fn key_bits(&self) -> [u8; KEY_BITS_LEN] {
+        let mut bits = [0u8; KEY_BITS_LEN];
+        let mut devices = self.devices();
+        let mut failed = vec![];
+        for device in &devices.list {
+            if device.read_key_bits(&mut bits).is_err() {
+                failed.push(device.path.clone());
+            }
+        }
+        devices.remove(&failed);
+        bits
+    }
+
+    /// Returns whether the given key or button code is pressed in `bits`.
+    pub fn is_pressed(bits: &[u8; KEY_BITS_LEN], code: u16) -> bool {
+        test_bit(bits, code as usize)
+    }
+
+    /// Returns the lock keys toggled on, merged over all devices.
+    pub fn lock_modifiers(&self) -> Modifiers {
+        self.devices()
+            .list
+            .iter()
+            .fold(Modifiers::empty(), |modifiers, device| {
+                modifiers | device.lock_modifiers()
+            })
+    }
+
+    /// Returns the character typed by the given key code with the keys currently held,
+    /// in the default XKB layout.
+    pub fn key_char(&self, code: u16) -> Option<char> {
+        let keymap = self.keymap.as_ref()?;
+        let bits = self.key_bits();
+        let pressed: Vec<u16> = (1..kernel_key::BTN_MISC)
+            .filter(|key| Self::is_pressed(&bits, *key))
+            .collect();
+        keymap.key_char(code, &pressed)
+    }
+
+    /// Applies the pending pointer events and returns the pointer position along with
+    /// the relative motion and the scroll deltas accumulated since the last call.
+    ///
+    /// Evdev has no notion of a cursor, so the position starts at `(0, 0)` and moves by
+    /// the relative motion of the mice and touchpads. Tablets and touchscreens set it
+    /// to where they are touched, mapped onto the console screen when there is a
+    /// framebuffer, in their own units from 0 otherwise.
+    pub fn poll_pointer(&self) -> (MousePosition, MouseDelta, ScrollDelta, HiResScroll) {
+        let mut pointer = match self.pointer.lock() {
+            Ok(pointer) => pointer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut devices = self.devices();
+        let mut failed = vec![];
+        let mut events = vec![];
+        for device in &mut devices.list {
+            events.clear();
+            if device.read_events(&mut events).is_err() {
+                failed.push(device.path.clone());
+            }
+            let notch = HiResScroll::NOTCH;
+            for event in &events {
+                match (event.type_, event.code) {
+                    (EV_REL, REL_X) => {
+                        pointer.coords.0 += event.value;
+                        pointer.motion_delta.0 += event.value;
+                    }
+                    (EV_REL, REL_Y) => {
+                        pointer.coords.1 += event.value;
+                        pointer.motion_delta.1 += event.value;
+                    }
+                    (EV_REL, REL_WHEEL) => {
+                        pointer.scroll_delta.vertical += event.value;
+                        if !device.has_hi_res_wheel {
+                            pointer.hi_res_scroll.vertical += event.value * notch;
+                        }
+                    }
+                    (EV_REL, REL_HWHEEL) => {
+                        pointer.scroll_delta.horizontal += event.value;
+                        if !device.has_hi_res_hwheel {
+                            pointer.hi_res_scroll.horizontal += event.value * notch;
+                        }
+                    }
+                    // Sent along with the low resolution events, in 1/120 of a notch.
+                    (EV_REL, REL_WHEEL_HI_RES) => pointer.hi_res_scroll.vertical += event.value,
+                    (EV_REL, REL_HWHEEL_HI_RES) => pointer.hi_res_scroll.horizontal += event.value,
+                    (EV_ABS, ABS_X) | (EV_ABS, ABS_Y) | (EV_KEY, BTN_TOUCH) => {
+                        if let Some(ref mut abs_pointer) = device.abs_pointer {
+                            Self::abs_event(&mut pointer, abs_pointer, event, self.screen_size);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        devices.remove(&failed);
+        (
+            pointer.coords,
+            mem::take(&mut pointer.motion_delta),
+            mem::take(&mut pointer.scroll_delta),
+            mem::take(&mut pointer.hi_res_scroll),
+        )
+    }
+    /// Moves the pointer with an event of an absolute device.
+    fn abs_event(
+        pointer: &mut PointerState,
+        abs_pointer: &mut AbsPointer,
+        event: &libc::input_event,
+        screen_size: Option<(i32, i32)>,
+    ) {
+        if !abs_pointer.touchpad {
+            match event.code {
+                ABS_X if event.type_ == EV_ABS => {
+                    pointer.coords.0 = abs_pointer.x.scale(event.value, screen_size.map(|s| s.0));
+                }
+                ABS_Y if event.type_ == EV_ABS => {
+                    pointer.coords.1 = abs_pointer.y.scale(event.value, screen_size.map(|s| s.1));
+                }
+                _ => {}
+            }
+            return;
+        }
+        // The finger moves the pointer from wherever it was, and lifting it ends the move.
+        let (last, coord, delta) = match (event.type_, event.code) {
+            (EV_ABS, ABS_X) => (
+                &mut abs_pointer.touch.0,
+                &mut pointer.coords.0,
+                &mut pointer.motion_delta.0,
+            ),
+            (EV_ABS, ABS_Y) => (
+                &mut abs_pointer.touch.1,
+                &mut pointer.coords.1,
+                &mut pointer.motion_delta.1,
+            ),
+            _ => {
+                if event.value == 0 {
+                    abs_pointer.touch = (None, None);
+                }
+                return;
+            }
+        };
+        if let Some(last) = last.replace(event.value) {
+            *coord += event.value - last;
+            *delta += event.value - last;
+        }
+    }
+}
@@ -1,6 +1,7 @@
-/// A non-exhaustive list of keycodes from Linux. Only the ones that this library currently supports
-/// is currently listed in this file; other keycodes will need to be added later as needed.
-/// Reference: https://github.com/torvalds/linux/blob/master/include/uapi/linux/input-event-codes.h
+//! The Linux kernel keycodes with a `Keycode` variant of their own, and the bounds of the key
+//! and button ranges. Every other kernel keycode is reported as `Keycode::Unknown` carrying the
+//! code itself, which maps back to the same kernel keycode, so any key round trips.
+//! Reference: https://github.com/torvalds/linux/blob/master/include/uapi/linux/input-event-codes.h
 
 pub const KEY_ESC: u16 = 1;
 pub const KEY_1: u16 = 2;
@@ -70,6 +71,8 @@ pub const KEY_F7: u16 = 65;
 pub const KEY_F8: u16 = 66;
 pub const KEY_F9: u16 = 67;
 pub const KEY_F10: u16 = 68;
+pub const KEY_NUMLOCK: u16 = 69;
+pub const KEY_SCROLLLOCK: u16 = 70;
 pub const KEY_KP7: u16 = 71;
 pub const KEY_KP8: u16 = 72;
 pub const KEY_KP9: u16 = 73;
@@ -83,8 +86,17 @@ pub const KEY_KP2: u16 = 80;
 pub const KEY_KP3: u16 = 81;
 pub const KEY_KP0: u16 = 82;
 pub const KEY_KPDOT: u16 = 83;
+pub const KEY_ZENKAKUHANKAKU: u16 = 85;
+pub const KEY_102ND: u16 = 86;
 pub const KEY_F11: u16 = 87;
 pub const KEY_F12: u16 = 88;
+pub const KEY_RO: u16 = 89;
+pub const KEY_KATAKANA: u16 = 90;
+pub const KEY_HIRAGANA: u16 = 91;
+pub const KEY_HENKAN: u16 = 92;
+pub const KEY_KATAKANAHIRAGANA: u16 = 93;
+pub const KEY_MUHENKAN: u16 = 94;
+pub const KEY_KPJPCOMMA: u16 = 95;
 pub const KEY_F13: u16 = 183;
 pub const KEY_F14: u16 = 184;
 pub const KEY_F15: u16 = 185;
@@ -96,6 +108,7 @@ pub const KEY_F20: u16 = 190;
 pub const KEY_KPENTER: u16 = 96;
 pub const KEY_RIGHTCTRL: u16 = 97;
 pub const KEY_KPSLASH: u16 = 98;
+pub const KEY_SYSRQ: u16 = 99;
 pub const KEY_RIGHTALT: u16 = 100;
 pub const KEY_HOME: u16 = 102;
 pub const KEY_UP: u16 = 103;
@@ -107,6 +120,75 @@ pub const KEY_DOWN: u16 = 108;
 pub const KEY_PAGEDOWN: u16 = 109;
 pub const KEY_INSERT: u16 = 110;
 pub const KEY_DELETE: u16 = 111;
+pub const KEY_MUTE: u16 = 113;
+pub const KEY_VOLUMEDOWN: u16 = 114;
+pub const KEY_VOLUMEUP: u16 = 115;
+pub const KEY_POWER: u16 = 116;
 pub const KEY_KPEQUAL: u16 = 117;
+pub const KEY_KPPLUSMINUS: u16 = 118;
+pub const KEY_PAUSE: u16 = 119;
+pub const KEY_KPCOMMA: u16 = 121;
+pub const KEY_HANGEUL: u16 = 122;
+pub const KEY_HANJA: u16 = 123;
+pub const KEY_YEN: u16 = 124;
 pub const KEY_LEFTMETA: u16 = 125;
 pub const KEY_RIGHTMETA: u16 = 126;
+pub const KEY_COMPOSE: u16 = 127;
+pub const KEY_STOP: u16 = 128;
+pub const KEY_AGAIN: u16 = 129;
+pub const KEY_PROPS: u16 = 130;
+pub const KEY_UNDO: u16 = 131;
+pub const KEY_FRONT: u16 = 132;
+pub const KEY_COPY: u16 = 133;
+pub const KEY_OPEN: u16 = 134;
+pub const KEY_PASTE: u16 = 135;
+pub const KEY_FIND: u16 = 136;
+pub const KEY_CUT: u16 = 137;
+pub const KEY_HELP: u16 = 138;
+pub const KEY_CALC: u16 = 140;
+pub const KEY_SLEEP: u16 = 142;
+pub const KEY_WAKEUP: u16 = 143;
+pub const KEY_MAIL: u16 = 155;
+pub const KEY_BOOKMARKS: u16 = 156;
+pub const KEY_COMPUTER: u16 = 157;
+pub const KEY_BACK: u16 = 158;
+pub const KEY_FORWARD: u16 = 159;
+pub const KEY_EJECTCD: u16 = 161;
+pub const KEY_NEXTSONG: u16 = 163;
+pub const KEY_PLAYPAUSE: u16 = 164;
+pub const KEY_PREVIOUSSONG: u16 = 165;
+pub const KEY_STOPCD: u16 = 166;
+pub const KEY_RECORD: u16 = 167;
+pub const KEY_REWIND: u16 = 168;
+pub const KEY_HOMEPAGE: u16 = 172;
+pub const KEY_REFRESH: u16 = 173;
+pub const KEY_KPLEFTPAREN: u16 = 179;
+pub const KEY_KPRIGHTPAREN: u16 = 180;
+pub const KEY_REDO: u16 = 182;
+pub const KEY_F21: u16 = 191;
+pub const KEY_F22: u16 = 192;
+pub const KEY_F23: u16 = 193;
+pub const KEY_F24: u16 = 194;
+pub const KEY_PLAYCD: u16 = 200;
+pub const KEY_PAUSECD: u16 = 201;
+pub const KEY_FASTFORWARD: u16 = 208;
+pub const KEY_SEARCH: u16 = 217;
+pub const KEY_BRIGHTNESSDOWN: u16 = 224;
+pub const KEY_BRIGHTNESSUP: u16 = 225;
+pub const KEY_MEDIA: u16 = 226;
+pub const KEY_MICMUTE: u16 = 248;
+
+pub const BTN_MISC: u16 = 0x100;
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+pub const BTN_SIDE: u16 = 0x113;
+pub const BTN_EXTRA: u16 = 0x114;
+pub const BTN_FORWARD: u16 = 0x115;
+pub const BTN_BACK: u16 = 0x116;
+pub const BTN_TASK: u16 = 0x117;
+
+pub const KEY_OK: u16 = 0x160;
+pub const BTN_TRIGGER_HAPPY: u16 = 0x2c0;
+
+pub const KEY_MAX: u16 = 0x2ff;
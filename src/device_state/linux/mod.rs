@@ -1,26 +1,266 @@
 extern crate x11;
 
+use self::evdev::EvdevDevices;
 use self::x11::xlib;
+use self::xinput2::{RawEvent, RawEventListener, XInput};
+use self::xrandr::XRandR;
+use self::xtest::XTest;
+use error::Error;
 use keymap::Keycode;
-use mouse_state::{MouseState, ScrollDelta};
+use modifiers::Modifiers;
+use monitor::Monitor;
+use mouse_state::{HiResScroll, MouseButton, MouseDelta, MousePosition, MouseState};
+use mouse_state::{ScrollDelta, ScrollSource};
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::mem;
 use std::os::raw::c_char;
 use std::ptr;
-use std::rc::Rc;
-use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Once, OnceLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+mod evdev;
 mod kernel_key;
+mod uinput;
+pub(crate) mod xinput2;
+mod xkb;
+mod xrandr;
+mod xtest;
 
-#[derive(Debug, Clone)]
+pub use self::uinput::VirtualDevice;
+
+/// `XkbUseCoreKbd`, the device id of the core keyboard.
+const XKB_USE_CORE_KBD: u32 = 0x0100;
+
+/// How long the monitors located by `query_pointer` are reused before asking the server again.
+const MONITORS_REFRESH: Duration = Duration::from_secs(1);
+
+/// How long the raw input thread waits for events before checking whether to stop.
+const RAW_INPUT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Scroll and relative motion accumulated for one `DeviceState`, from the XInput2 raw
+/// events with X11 and from the devices polled with evdev.
+#[derive(Debug, Default)]
+struct RawInput {
+    scroll_vertical: AtomicI32,
+    scroll_horizontal: AtomicI32,
+    motion_x: AtomicI32,
+    motion_y: AtomicI32,
+    hi_res_vertical: AtomicI32,
+    hi_res_horizontal: AtomicI32,
+    hi_res_continuous: AtomicBool,
+}
+
+impl RawInput {
+    /// Reads and resets the deltas accumulated since the last call.
+    fn take(&self) -> (ScrollDelta, MouseDelta, HiResScroll) {
+        let scroll_delta = ScrollDelta {
+            vertical: self.scroll_vertical.swap(0, Ordering::Relaxed),
+            horizontal: self.scroll_horizontal.swap(0, Ordering::Relaxed),
+        };
+        let motion_delta = (
+            self.motion_x.swap(0, Ordering::Relaxed),
+            self.motion_y.swap(0, Ordering::Relaxed),
+        );
+        let hi_res_scroll = HiResScroll {
+            vertical: self.hi_res_vertical.swap(0, Ordering::Relaxed),
+            horizontal: self.hi_res_horizontal.swap(0, Ordering::Relaxed),
+            source: if self.hi_res_continuous.load(Ordering::Relaxed) {
+                ScrollSource::Continuous
+            } else {
+                ScrollSource::Wheel
+            },
+        };
+        (scroll_delta, motion_delta, hi_res_scroll)
+    }
+
+    fn add_deltas(&self, scroll: ScrollDelta, motion: MouseDelta, hi_res_scroll: HiResScroll) {
+        self.scroll_vertical
+            .fetch_add(scroll.vertical, Ordering::Relaxed);
+        self.scroll_horizontal
+            .fetch_add(scroll.horizontal, Ordering::Relaxed);
+        self.add(RawEvent::Motion(motion));
+        if !hi_res_scroll.is_zero() {
+            self.add(RawEvent::Scroll(hi_res_scroll));
+        }
+    }
+
+    fn add(&self, event: RawEvent) {
+        // In X11, buttons 4/5 are scroll up/down, buttons 6/7 are scroll left/right.
+        match event {
+            RawEvent::Motion((dx, dy)) => {
+                self.motion_x.fetch_add(dx, Ordering::Relaxed);
+                self.motion_y.fetch_add(dy, Ordering::Relaxed);
+            }
+            RawEvent::Scroll(scroll) => {
+                let continuous = scroll.source == ScrollSource::Continuous;
+                self.hi_res_continuous.store(continuous, Ordering::Relaxed);
+                self.hi_res_vertical
+                    .fetch_add(scroll.vertical, Ordering::Relaxed);
+                self.hi_res_horizontal
+                    .fetch_add(scroll.horizontal, Ordering::Relaxed);
+            }
+            RawEvent::ButtonDown(button @ 4..=7) => {
+                let (vertical, horizontal) = match button {
+                    4 => (1, 0),
+                    5 => (-1, 0),
+                    6 => (0, -1),
+                    _ => (0, 1),
+                };
+                self.scroll_vertical.fetch_add(vertical, Ordering::Relaxed);
+                self.scroll_horizontal
+                    .fetch_add(horizontal, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The deltas of a `DeviceState` and of its clones, each reading and resetting its own.
+#[derive(Debug, Default)]
+struct RawInputs {
+    inputs: Mutex<Vec<Weak<RawInput>>>,
+}
+
+impl RawInputs {
+    fn subscribe(&self) -> Arc<RawInput> {
+        let input = Arc::new(RawInput::default());
+        self.inputs().push(Arc::downgrade(&input));
+        input
+    }
+
+    /// Runs `f` on the deltas of every `DeviceState` still alive.
+    fn for_each<F: Fn(&RawInput)>(&self, f: F) {
+        self.inputs().retain(|input| match input.upgrade() {
+            Some(input) => {
+                f(&input);
+                true
+            }
+            None => false,
+        });
+    }
+
+    fn inputs(&self) -> MutexGuard<'_, Vec<Weak<RawInput>>> {
+        match self.inputs.lock() {
+            Ok(inputs) => inputs,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Wheel "presses" are instantaneous and almost never visible in a `XQueryPointer`
+/// snapshot, and relative motion isn't part of it at all, so they are captured from
+/// XInput2 raw events on a dedicated connection. Each `DeviceState` opened has its own,
+/// shared with its clones, and the thread is stopped and joined when the last of them
+/// is dropped.
+#[derive(Debug)]
+struct RawInputListener {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RawInputListener {
+    fn spawn(display_name: &str, inputs: Arc<RawInputs>) -> Option<RawInputListener> {
+        let mut listener = RawEventListener::open(Some(display_name)).ok()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let Ok(events) = listener.next_events(RAW_INPUT_TIMEOUT) else {
+                    break;
+                };
+                for (event, _) in events {
+                    inputs.for_each(|input| input.add(event.clone()));
+                }
+            }
+        });
+        Some(RawInputListener {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for RawInputListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Input backends the Linux `DeviceState` can read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Query the X server through Xlib. Requires a running X server (or XWayland).
+    X11,
+    /// Read `/dev/input/event*` directly. Works without any display server, but the
+    /// process needs read access to the devices, usually through the `input` group.
+    Evdev,
+}
+
+impl Default for Backend {
+    /// `Evdev` when the `evdev` feature is enabled, `X11` otherwise.
+    fn default() -> Self {
+        if cfg!(feature = "evdev") {
+            Backend::Evdev
+        } else {
+            Backend::X11
+        }
+    }
+}
+
+#[derive(Debug)]
 /// Device state descriptor.
+///
+/// Clones share the connection to the devices, but each of them has its own motion and
+/// scroll deltas: querying one doesn't reset those of the others.
 pub struct DeviceState {
-    xc: Rc<X11Connection>,
+    conn: Arc<Connection>,
+    inputs: Arc<RawInputs>,
+    /// The deltas of this `DeviceState`, registered in `inputs`.
+    input: Arc<RawInput>,
+}
+
+impl Clone for DeviceState {
+    fn clone(&self) -> Self {
+        DeviceState {
+            conn: self.conn.clone(),
+            inputs: self.inputs.clone(),
+            input: self.inputs.subscribe(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Connection {
+    X11(X11Connection),
+    Evdev(EvdevDevices),
 }
 
 #[derive(Debug)]
 struct X11Connection {
     display: *mut xlib::Display,
+    /// Loaded the first time an input event is synthesized.
+    xtest: OnceLock<Result<XTest, Error>>,
+    /// Loaded the first time the pointer is queried or raw events are selected.
+    xinput: OnceLock<Result<XInput, Error>>,
+    /// Loaded the first time the monitors are queried.
+    xrandr: OnceLock<Result<XRandR, Error>>,
+    /// Monitors last queried, and when.
+    monitors: Mutex<Option<(Instant, Vec<Monitor>)>>,
+    /// Deltas read from the raw events, for the connections of a `DeviceState`.
+    raw_input: Option<RawInputListener>,
 }
 
+// Xlib serializes the requests on a display once `XInitThreads` has been called,
+// which `X11Connection::open` does before opening any.
+unsafe impl Send for X11Connection {}
+unsafe impl Sync for X11Connection {}
+
 impl Drop for X11Connection {
     fn drop(&mut self) {
         unsafe {
@@ -29,36 +269,349 @@ impl Drop for X11Connection {
     }
 }
 
+impl X11Connection {
+    /// Connects to the named display, or to `$DISPLAY` when `None`.
+    fn open(display_name: Option<&str>) -> Result<X11Connection, Error> {
+        static INIT_THREADS: Once = Once::new();
+        let display_name = match display_name {
+            Some(name) => Some(CString::new(name).map_err(|_| Error::NoDisplay)?),
+            None => None,
+        };
+        let name = display_name
+            .as_ref()
+            .map_or(ptr::null(), |name| name.as_ptr());
+        unsafe {
+            INIT_THREADS.call_once(|| {
+                xlib::XInitThreads();
+            });
+            let display = xlib::XOpenDisplay(name);
+            if display.is_null() {
+                return Err(Error::NoDisplay);
+            }
+            Ok(X11Connection {
+                display,
+                xtest: OnceLock::new(),
+                xinput: OnceLock::new(),
+                xrandr: OnceLock::new(),
+                monitors: Mutex::new(None),
+                raw_input: None,
+            })
+        }
+    }
+
+    /// Returns the name of the display, such as `":0"`.
+    fn display_name(&self) -> String {
+        unsafe { CStr::from_ptr(xlib::XDisplayString(self.display)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn xinput(&self) -> &Result<XInput, Error> {
+        self.xinput.get_or_init(|| XInput::open(self))
+    }
+
+    fn xrandr(&self) -> &Result<XRandR, Error> {
+        self.xrandr.get_or_init(|| XRandR::open(self))
+    }
+
+    fn monitors(&self) -> MutexGuard<'_, Option<(Instant, Vec<Monitor>)>> {
+        match self.monitors.lock() {
+            Ok(monitors) => monitors,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// The kernel codes of the mouse buttons, in the order of [`MouseButton::number`]. The
+/// X server numbers them the same way, after the wheel buttons.
+const MOUSE_BUTTONS: [u16; 8] = [
+    kernel_key::BTN_LEFT,
+    kernel_key::BTN_MIDDLE,
+    kernel_key::BTN_RIGHT,
+    kernel_key::BTN_SIDE,
+    kernel_key::BTN_EXTRA,
+    kernel_key::BTN_FORWARD,
+    kernel_key::BTN_BACK,
+    kernel_key::BTN_TASK,
+];
+
+/// Converts an X11 button number, `None` for the wheel buttons 4 to 7.
+pub(crate) fn mouse_button_from_x11(button: u32) -> Option<MouseButton> {
+    match button {
+        1..=3 => MouseButton::from_number(button as usize),
+        4..=7 => None,
+        button => MouseButton::from_number(button as usize - 4),
+    }
+}
+
+fn mouse_button_to_x11(button: MouseButton) -> Option<u8> {
+    match button.number() {
+        0 => None,
+        number @ 1..=3 => Some(number as u8),
+        number => u8::try_from(number + 4).ok(),
+    }
+}
+
 impl DeviceState {
-    /// Creates a new DeviceState.
+    /// Creates a new DeviceState using the default [`Backend`].
     pub fn new() -> DeviceState {
+        Self::with_backend(Backend::default())
+    }
+
+    /// Create a new DeviceState. In case of failure, doesn't panic.
+    pub fn checked_new() -> Option<DeviceState> {
+        Self::try_new().ok()
+    }
+
+    /// Create a new DeviceState, returning why it failed instead of panicking.
+    pub fn try_new() -> Result<DeviceState, Error> {
+        Self::try_with_backend(Backend::default())
+    }
+
+    /// Creates a new DeviceState reading from the given backend.
+    pub fn with_backend(backend: Backend) -> DeviceState {
+        Self::try_with_backend(backend).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Create a new DeviceState reading from the given backend. In case of failure, doesn't panic.
+    pub fn checked_with_backend(backend: Backend) -> Option<DeviceState> {
+        Self::try_with_backend(backend).ok()
+    }
+
+    /// Create a new DeviceState reading from the given backend, returning why it failed
+    /// instead of panicking.
+    pub fn try_with_backend(backend: Backend) -> Result<DeviceState, Error> {
+        let inputs = Arc::new(RawInputs::default());
+        let conn = match backend {
+            Backend::X11 => Self::open_x11(None, &inputs)?,
+            Backend::Evdev => Connection::Evdev(EvdevDevices::open()?),
+        };
+        Ok(Self::with_connection(conn, inputs))
+    }
+
+    /// Creates a new DeviceState reading from the X11 backend on the given display, such
+    /// as `":1"`, instead of the one in `$DISPLAY`.
+    pub fn with_display(display_name: &str) -> DeviceState {
+        Self::try_with_display(display_name).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Create a new DeviceState reading from the given X display. In case of failure, doesn't panic.
+    pub fn checked_with_display(display_name: &str) -> Option<DeviceState> {
+        Self::try_with_display(display_name).ok()
+    }
+
+    /// Create a new DeviceState reading from the given X display, returning
+    /// [`Error::NoDisplay`] when it can't be reached instead of panicking.
+    pub fn try_with_display(display_name: &str) -> Result<DeviceState, Error> {
+        let inputs = Arc::new(RawInputs::default());
+        let conn = Self::open_x11(Some(display_name), &inputs)?;
+        Ok(Self::with_connection(conn, inputs))
+    }
+
+    fn open_x11(display_name: Option<&str>, inputs: &Arc<RawInputs>) -> Result<Connection, Error> {
+        let mut xc = X11Connection::open(display_name)?;
+        xc.raw_input = RawInputListener::spawn(&xc.display_name(), inputs.clone());
+        Ok(Connection::X11(xc))
+    }
+
+    fn with_connection(conn: Connection, inputs: Arc<RawInputs>) -> DeviceState {
+        DeviceState {
+            conn: Arc::new(conn),
+            input: inputs.subscribe(),
+            inputs,
+        }
+    }
+
+    /// Returns the backend this DeviceState reads from.
+    pub fn backend(&self) -> Backend {
+        match *self.conn {
+            Connection::X11(_) => Backend::X11,
+            Connection::Evdev(_) => Backend::Evdev,
+        }
+    }
+
+    /// Query the `MouseState`.
+    pub fn query_pointer(&self) -> MouseState {
+        match *self.conn {
+            Connection::X11(ref xc) => self.query_x11_pointer(xc),
+            Connection::Evdev(ref devices) => self.query_evdev_pointer(devices),
+        }
+    }
+
+    /// Same as [`query_pointer`](Self::query_pointer), failing with
+    /// [`Error::ConnectionLost`] once after an evdev device was unplugged. The devices
+    /// left keep being read, along with those plugged since.
+    pub fn try_query_pointer(&self) -> Result<MouseState, Error> {
+        let mouse_state = self.query_pointer();
+        self.check_devices()?;
+        Ok(mouse_state)
+    }
+
+    /// Query the monitors of the default X screen with XRandR, or the whole screen as a
+    /// single monitor without XRandR 1.5. Empty with the evdev backend, which has no screen.
+    pub fn query_monitors(&self) -> Vec<Monitor> {
+        match *self.conn {
+            Connection::X11(ref xc) => {
+                let monitors = Self::query_x11_monitors(xc);
+                *xc.monitors() = Some((Instant::now(), monitors.clone()));
+                monitors
+            }
+            Connection::Evdev(_) => vec![],
+        }
+    }
+
+    /// Query the Keyboard state.
+    pub fn query_keymap(&self) -> Vec<Keycode> {
+        match *self.conn {
+            Connection::X11(ref xc) => Self::query_x11_keymap(xc),
+            Connection::Evdev(ref devices) => Self::query_evdev_keymap(devices),
+        }
+    }
+
+    /// Same as [`query_keymap`](Self::query_keymap), failing with
+    /// [`Error::ConnectionLost`] once after an evdev device was unplugged.
+    pub fn try_query_keymap(&self) -> Result<Vec<Keycode>, Error> {
+        let keys = self.query_keymap();
+        self.check_devices()?;
+        Ok(keys)
+    }
+
+    fn check_devices(&self) -> Result<(), Error> {
+        match *self.conn {
+            Connection::Evdev(ref devices) if devices.take_lost() => Err(Error::ConnectionLost),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the name of a raw kernel keycode, such as the one carried by
+    /// [`Keycode::Unknown`]. This is the X keysym name of the key in the current layout,
+    /// so it is not available with the evdev backend.
+    pub fn raw_key_name(&self, code: u16) -> Option<String> {
+        let Connection::X11(ref xc) = *self.conn else {
+            return None;
+        };
+        //x11 keycode uses kernel keycode with an offset of 8.
+        let x11_key = u8::try_from(code.checked_add(8)?).ok()?;
         unsafe {
-            let display = xlib::XOpenDisplay(ptr::null());
-            if display.as_ref().is_none() {
-                panic!("Could not connect to a X display");
+            let keysym = xlib::XkbKeycodeToKeysym(xc.display, x11_key, 0, 0);
+            if keysym == 0 {
+                return None;
             }
-            DeviceState {
-                xc: Rc::new(X11Connection { display }),
+            let name = xlib::XKeysymToString(keysym);
+            if name.is_null() {
+                return None;
             }
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
         }
     }
 
-    /// Create a new DeviceState. In case of failure, doesn't panic.
-    pub fn checked_new() -> Option<DeviceState> {
+    /// Query the modifiers held and the lock keys toggled on.
+    pub fn query_modifiers(&self) -> Modifiers {
+        let locks = match *self.conn {
+            Connection::X11(ref xc) => Self::x11_lock_modifiers(xc),
+            Connection::Evdev(ref devices) => devices.lock_modifiers(),
+        };
+        Modifiers::from_keys(&self.query_keymap()) | locks
+    }
+
+    /// Returns the lock keys toggled on according to the keyboard indicators.
+    fn x11_lock_modifiers(xc: &X11Connection) -> Modifiers {
+        let mut indicators = 0;
+        unsafe {
+            xlib::XkbGetIndicatorState(xc.display, XKB_USE_CORE_KBD, &mut indicators);
+        }
+        // Indicators of the default XKB configuration.
+        let mut modifiers = Modifiers::empty();
+        modifiers.set(Modifiers::CAPS_LOCK, indicators & 0x1 != 0);
+        modifiers.set(Modifiers::NUM_LOCK, indicators & 0x2 != 0);
+        modifiers.set(Modifiers::SCROLL_LOCK, indicators & 0x4 != 0);
+        modifiers
+    }
+
+    /// Returns the character typed by the key in the current keyboard layout with the
+    /// modifiers currently held.
+    pub fn query_char(&self, keycode: Keycode) -> Option<char> {
+        let kernel_key = keycode_to_kernel_key(keycode)?;
+        match *self.conn {
+            Connection::X11(ref xc) => Self::x11_char(xc, kernel_key),
+            Connection::Evdev(ref devices) => devices.key_char(kernel_key),
+        }
+    }
+
+    /// Press or release a key.
+    pub fn send_key(&self, key: Keycode, pressed: bool) -> Result<(), Error> {
+        let (xc, xtest) = self.x11_control()?;
+        //x11 keycode uses kernel keycode with an offset of 8.
+        let x11_key = keycode_to_kernel_key(key)
+            .and_then(|code| code.checked_add(8))
+            .and_then(|code| u8::try_from(code).ok())
+            .ok_or_else(|| Error::UnsupportedInput(format!("no X11 keycode for {}", key)))?;
+        xtest.key(xc, x11_key, pressed);
+        Ok(())
+    }
+
+    /// Move the mouse to a position of the screen.
+    pub fn send_mouse_move(&self, position: MousePosition) -> Result<(), Error> {
+        let (xc, xtest) = self.x11_control()?;
+        xtest.motion(xc, position);
+        Ok(())
+    }
+
+    /// Press or release a mouse button.
+    pub fn send_mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        let (xc, xtest) = self.x11_control()?;
+        let x11_button = mouse_button_to_x11(button)
+            .ok_or_else(|| Error::UnsupportedInput(format!("mouse button {}", button)))?;
+        xtest.button(xc, x11_button, pressed);
+        Ok(())
+    }
+
+    /// Scroll the mouse wheel.
+    pub fn send_scroll(&self, delta: ScrollDelta) -> Result<(), Error> {
+        let (xc, xtest) = self.x11_control()?;
+        xtest.scroll(xc, delta);
+        Ok(())
+    }
+
+    /// Returns the X connection along with its XTest functions.
+    fn x11_control(&self) -> Result<(&X11Connection, &XTest), Error> {
+        match *self.conn {
+            Connection::X11(ref xc) => match *xc.xtest.get_or_init(|| XTest::open(xc)) {
+                Ok(ref xtest) => Ok((xc, xtest)),
+                Err(ref error) => Err(error.clone()),
+            },
+            Connection::Evdev(_) => Err(Error::BackendUnavailable(
+                "synthesizing input requires the X11 backend, use VirtualDevice instead".to_string(),
+            )),
+        }
+    }
+
+    fn x11_char(xc: &X11Connection, kernel_key: u16) -> Option<char> {
+        //x11 keycode uses kernel keycode with an offset of 8.
+        let x11_key = u8::try_from(kernel_key.checked_add(8)?).ok()?;
+        let mut keysym = 0;
         unsafe {
-            let display = xlib::XOpenDisplay(ptr::null());
-            if display.as_ref().is_none() {
-                eprintln!("Could not connect to a X display");
+            let mut state: xlib::XkbStateRec = mem::zeroed();
+            xlib::XkbGetState(xc.display, XKB_USE_CORE_KBD, &mut state);
+            // Same layout as the state of key events: the modifiers, then the group at bit 13.
+            let event_state = state.mods as u32 | (state.group as u32) << 13;
+            let mut consumed_mods = 0;
+            if xlib::XkbLookupKeySym(
+                xc.display,
+                x11_key,
+                event_state,
+                &mut consumed_mods,
+                &mut keysym,
+            ) == 0
+            {
                 return None;
             }
-            Some(DeviceState {
-                xc: Rc::new(X11Connection { display }),
-            })
         }
+        xkb::keysym_to_char(keysym as u32)
     }
 
-    /// Query the `MouseState`.
-    pub fn query_pointer(&self) -> MouseState {
+    /// Returns the pointer coordinates and the buttons and modifiers mask.
+    fn x11_pointer(xc: &X11Connection) -> (MousePosition, u32) {
         let root;
         let mut root_x = 0;
         let mut root_y = 0;
@@ -68,9 +621,9 @@ impl DeviceState {
         let mut child_return = 0;
         let mut mask_return = 0;
         unsafe {
-            root = xlib::XDefaultRootWindow(self.xc.display);
+            root = xlib::XDefaultRootWindow(xc.display);
             xlib::XQueryPointer(
-                self.xc.display,
+                xc.display,
                 root,
                 &mut root_return,
                 &mut child_return,
@@ -81,175 +634,525 @@ impl DeviceState {
                 &mut mask_return,
             );
         }
-        let button1pressed = mask_return & xlib::Button1Mask > 0;
-        let button2pressed = mask_return & xlib::Button2Mask > 0;
-        let button3pressed = mask_return & xlib::Button3Mask > 0;
-        let button4pressed = mask_return & xlib::Button4Mask > 0;
-        let button5pressed = mask_return & xlib::Button5Mask > 0;
-
-        // In X11, buttons 4/5 are scroll up/down, buttons 6/7 are scroll left/right.
-        // Masks for buttons 6/7 are at bits 13/14.
-        const BUTTON6_MASK: u32 = 1 << 13;
-        const BUTTON7_MASK: u32 = 1 << 14;
-        let button6pressed = mask_return & BUTTON6_MASK > 0;
-        let button7pressed = mask_return & BUTTON7_MASK > 0;
+        ((win_x, win_y), mask_return)
+    }
 
-        let scroll_delta = ScrollDelta {
-            vertical: if button4pressed { 1 } else if button5pressed { -1 } else { 0 },
-            horizontal: if button7pressed { 1 } else if button6pressed { -1 } else { 0 },
+    fn query_x11_pointer(&self, xc: &X11Connection) -> MouseState {
+        let (coords, mask_return) = Self::x11_pointer(xc);
+        // The core protocol only reports buttons 1 to 5, of which 4 and 5 are the wheel.
+        let pressed_buttons = match *xc.xinput() {
+            Ok(ref xinput) => xinput.pressed_buttons(xc),
+            Err(_) => [(1, xlib::Button1Mask), (2, xlib::Button2Mask), (3, xlib::Button3Mask)]
+                .iter()
+                .filter(|(_, mask)| mask_return & mask != 0)
+                .map(|(button, _)| *button)
+                .collect(),
         };
 
+        // Read and reset scroll delta atomically
+        let (scroll_delta, motion_delta, hi_res_scroll) = self.input.take();
+
         // Use 1-based indexing here so people can just query the button
         // number they're interested in directly.
-        let button_pressed = vec![
-            false,
-            button1pressed,
-            button2pressed,
-            button3pressed,
-            button4pressed,
-            button5pressed,
-        ];
-        MouseState {
-            coords: (win_x, win_y),
-            button_pressed,
+        let mut mouse_state = MouseState {
+            coords,
+            button_pressed: vec![false; 6],
             scroll_delta,
+            hi_res_scroll,
+            motion_delta,
+            monitor: None,
+            monitor_coords: coords,
+        };
+        for button in pressed_buttons.into_iter().filter_map(mouse_button_from_x11) {
+            mouse_state.set_pressed(button, true);
         }
+        Self::locate_x11_monitor(xc, &mut mouse_state);
+        mouse_state
     }
 
-    /// Query the Keyboard state.
-    pub fn query_keymap(&self) -> Vec<Keycode> {
+    /// Locates the pointer on the monitors, queried again when the last query is older
+    /// than `MONITORS_REFRESH` so that polling doesn't cost another round trip each time.
+    fn locate_x11_monitor(xc: &X11Connection, mouse_state: &mut MouseState) {
+        let mut monitors = xc.monitors();
+        let fresh = matches!(*monitors, Some((queried, _)) if queried.elapsed() < MONITORS_REFRESH);
+        if !fresh {
+            *monitors = Some((Instant::now(), Self::query_x11_monitors(xc)));
+        }
+        if let Some((_, ref monitors)) = *monitors {
+            mouse_state.locate_monitor(monitors);
+        }
+    }
+
+    fn query_x11_monitors(xc: &X11Connection) -> Vec<Monitor> {
+        let scale_factor = Self::x11_scale_factor(xc);
+        let monitors = match *xc.xrandr() {
+            Ok(ref xrandr) => xrandr.monitors(xc, scale_factor),
+            Err(_) => vec![],
+        };
+        if !monitors.is_empty() {
+            return monitors;
+        }
+        unsafe {
+            let screen = xlib::XDefaultScreen(xc.display);
+            vec![Monitor {
+                name: format!("Screen {}", screen),
+                position: (0, 0),
+                size: (
+                    xlib::XDisplayWidth(xc.display, screen).max(0) as u32,
+                    xlib::XDisplayHeight(xc.display, screen).max(0) as u32,
+                ),
+                scale_factor,
+                primary: true,
+            }]
+        }
+    }
+
+    /// Returns the scale factor desktops set with the `Xft.dpi` resource, or 1 when unset.
+    fn x11_scale_factor(xc: &X11Connection) -> f64 {
+        let resources = unsafe { xlib::XResourceManagerString(xc.display) };
+        if resources.is_null() {
+            return 1.0;
+        }
+        let resources = unsafe { CStr::from_ptr(resources) }.to_string_lossy();
+        resources
+            .lines()
+            .filter_map(|line| line.strip_prefix("Xft.dpi:"))
+            .filter_map(|dpi| dpi.trim().parse::<f64>().ok())
+            .find(|dpi| *dpi > 0.0)
+            .map_or(1.0, |dpi| dpi / 96.0)
+    }
+
+    fn query_x11_keymap(xc: &X11Connection) -> Vec<Keycode> {
         let mut keycodes = vec![];
+        let mut keymap: [c_char; 32] = [0; 32];
         unsafe {
-            let keymap: *mut c_char = [0; 32].as_mut_ptr();
-            xlib::XQueryKeymap(self.xc.display, keymap);
-            for (ix, byte) in slice::from_raw_parts(keymap, 32).iter().enumerate() {
-                for bit in 0_u8..8_u8 {
-                    let bitmask = 1 << bit;
-                    if byte & bitmask != 0 {
-                        //x11 keycode uses kernel keycode with an offset of 8.
-                        let x11_key = ix as u8 * 8 + bit;
-                        let kernel_key = x11_key - 8;
-                        if let Some(k) = self.kernel_key_to_keycode(kernel_key) {
-                            keycodes.push(k)
-                        }
-                    }
+            xlib::XQueryKeymap(xc.display, keymap.as_mut_ptr());
+        }
+        for (ix, byte) in keymap.iter().enumerate() {
+            for bit in 0_u8..8_u8 {
+                let bitmask = 1 << bit;
+                if byte & bitmask != 0 {
+                    //x11 keycode uses kernel keycode with an offset of 8.
+                    let x11_key = ix as u16 * 8 + bit as u16;
+                    let Some(kernel_key) = x11_key.checked_sub(8) else {
+                        continue;
+                    };
+                    keycodes.push(kernel_key_to_keycode(kernel_key));
                 }
             }
         }
         keycodes
     }
 
-    fn kernel_key_to_keycode(&self, kernel_code: u8) -> Option<Keycode> {
-        match kernel_code as u16 {
-            kernel_key::KEY_0 => Some(Keycode::Key0),
-            kernel_key::KEY_1 => Some(Keycode::Key1),
-            kernel_key::KEY_2 => Some(Keycode::Key2),
-            kernel_key::KEY_3 => Some(Keycode::Key3),
-            kernel_key::KEY_4 => Some(Keycode::Key4),
-            kernel_key::KEY_5 => Some(Keycode::Key5),
-            kernel_key::KEY_6 => Some(Keycode::Key6),
-            kernel_key::KEY_7 => Some(Keycode::Key7),
-            kernel_key::KEY_8 => Some(Keycode::Key8),
-            kernel_key::KEY_9 => Some(Keycode::Key9),
-            kernel_key::KEY_A => Some(Keycode::A),
-            kernel_key::KEY_B => Some(Keycode::B),
-            kernel_key::KEY_C => Some(Keycode::C),
-            kernel_key::KEY_D => Some(Keycode::D),
-            kernel_key::KEY_E => Some(Keycode::E),
-            kernel_key::KEY_F => Some(Keycode::F),
-            kernel_key::KEY_G => Some(Keycode::G),
-            kernel_key::KEY_H => Some(Keycode::H),
-            kernel_key::KEY_I => Some(Keycode::I),
-            kernel_key::KEY_J => Some(Keycode::J),
-            kernel_key::KEY_K => Some(Keycode::K),
-            kernel_key::KEY_L => Some(Keycode::L),
-            kernel_key::KEY_M => Some(Keycode::M),
-            kernel_key::KEY_N => Some(Keycode::N),
-            kernel_key::KEY_O => Some(Keycode::O),
-            kernel_key::KEY_P => Some(Keycode::P),
-            kernel_key::KEY_Q => Some(Keycode::Q),
-            kernel_key::KEY_R => Some(Keycode::R),
-            kernel_key::KEY_S => Some(Keycode::S),
-            kernel_key::KEY_T => Some(Keycode::T),
-            kernel_key::KEY_U => Some(Keycode::U),
-            kernel_key::KEY_V => Some(Keycode::V),
-            kernel_key::KEY_W => Some(Keycode::W),
-            kernel_key::KEY_X => Some(Keycode::X),
-            kernel_key::KEY_Y => Some(Keycode::Y),
-            kernel_key::KEY_Z => Some(Keycode::Z),
-            kernel_key::KEY_F1 => Some(Keycode::F1),
-            kernel_key::KEY_F2 => Some(Keycode::F2),
-            kernel_key::KEY_F3 => Some(Keycode::F3),
-            kernel_key::KEY_F4 => Some(Keycode::F4),
-            kernel_key::KEY_F5 => Some(Keycode::F5),
-            kernel_key::KEY_F6 => Some(Keycode::F6),
-            kernel_key::KEY_F7 => Some(Keycode::F7),
-            kernel_key::KEY_F8 => Some(Keycode::F8),
-            kernel_key::KEY_F9 => Some(Keycode::F9),
-            kernel_key::KEY_F10 => Some(Keycode::F10),
-            kernel_key::KEY_F11 => Some(Keycode::F11),
-            kernel_key::KEY_F12 => Some(Keycode::F12),
-            kernel_key::KEY_F13 => Some(Keycode::F13),
-            kernel_key::KEY_F14 => Some(Keycode::F14),
-            kernel_key::KEY_F15 => Some(Keycode::F15),
-            kernel_key::KEY_F16 => Some(Keycode::F16),
-            kernel_key::KEY_F17 => Some(Keycode::F17),
-            kernel_key::KEY_F18 => Some(Keycode::F18),
-            kernel_key::KEY_F19 => Some(Keycode::F19),
-            kernel_key::KEY_F20 => Some(Keycode::F20),
-            kernel_key::KEY_KP0 => Some(Keycode::Numpad0),
-            kernel_key::KEY_KP1 => Some(Keycode::Numpad1),
-            kernel_key::KEY_KP2 => Some(Keycode::Numpad2),
-            kernel_key::KEY_KP3 => Some(Keycode::Numpad3),
-            kernel_key::KEY_KP4 => Some(Keycode::Numpad4),
-            kernel_key::KEY_KP5 => Some(Keycode::Numpad5),
-            kernel_key::KEY_KP6 => Some(Keycode::Numpad6),
-            kernel_key::KEY_KP7 => Some(Keycode::Numpad7),
-            kernel_key::KEY_KP8 => Some(Keycode::Numpad8),
-            kernel_key::KEY_KP9 => Some(Keycode::Numpad9),
-            kernel_key::KEY_KPENTER => Some(Keycode::NumpadEnter),
-            kernel_key::KEY_KPMINUS => Some(Keycode::NumpadSubtract),
-            kernel_key::KEY_KPPLUS => Some(Keycode::NumpadAdd),
-            kernel_key::KEY_KPSLASH => Some(Keycode::NumpadDivide),
-            kernel_key::KEY_KPASTERISK => Some(Keycode::NumpadMultiply),
-            kernel_key::KEY_KPEQUAL => Some(Keycode::NumpadEquals),
-            kernel_key::KEY_KPDOT => Some(Keycode::NumpadDecimal),
-            kernel_key::KEY_ESC => Some(Keycode::Escape),
-            kernel_key::KEY_SPACE => Some(Keycode::Space),
-            kernel_key::KEY_LEFTCTRL => Some(Keycode::LControl),
-            kernel_key::KEY_RIGHTCTRL => Some(Keycode::RControl),
-            kernel_key::KEY_LEFTSHIFT => Some(Keycode::LShift),
-            kernel_key::KEY_RIGHTSHIFT => Some(Keycode::RShift),
-            kernel_key::KEY_LEFTALT => Some(Keycode::LAlt),
-            kernel_key::KEY_RIGHTALT => Some(Keycode::RAlt),
-            kernel_key::KEY_LEFTMETA => Some(Keycode::LMeta),
-            kernel_key::KEY_RIGHTMETA => Some(Keycode::RMeta),
-            kernel_key::KEY_ENTER => Some(Keycode::Enter),
-            kernel_key::KEY_UP => Some(Keycode::Up),
-            kernel_key::KEY_DOWN => Some(Keycode::Down),
-            kernel_key::KEY_LEFT => Some(Keycode::Left),
-            kernel_key::KEY_RIGHT => Some(Keycode::Right),
-            kernel_key::KEY_BACKSPACE => Some(Keycode::Backspace),
-            kernel_key::KEY_CAPSLOCK => Some(Keycode::CapsLock),
-            kernel_key::KEY_TAB => Some(Keycode::Tab),
-            kernel_key::KEY_HOME => Some(Keycode::Home),
-            kernel_key::KEY_END => Some(Keycode::End),
-            kernel_key::KEY_PAGEUP => Some(Keycode::PageUp),
-            kernel_key::KEY_PAGEDOWN => Some(Keycode::PageDown),
-            kernel_key::KEY_INSERT => Some(Keycode::Insert),
-            kernel_key::KEY_DELETE => Some(Keycode::Delete),
-            kernel_key::KEY_GRAVE => Some(Keycode::Grave),
-            kernel_key::KEY_MINUS => Some(Keycode::Minus),
-            kernel_key::KEY_EQUAL => Some(Keycode::Equal),
-            kernel_key::KEY_LEFTBRACE => Some(Keycode::LeftBracket),
-            kernel_key::KEY_RIGHTBRACE => Some(Keycode::RightBracket),
-            kernel_key::KEY_BACKSLASH => Some(Keycode::BackSlash),
-            kernel_key::KEY_SEMICOLON => Some(Keycode::Semicolon),
-            kernel_key::KEY_APOSTROPHE => Some(Keycode::Apostrophe),
-            kernel_key::KEY_COMMA => Some(Keycode::Comma),
-            kernel_key::KEY_DOT => Some(Keycode::Dot),
-            kernel_key::KEY_SLASH => Some(Keycode::Slash),
-            _ => None,
+    fn query_evdev_pointer(&self, devices: &EvdevDevices) -> MouseState {
+        let (coords, motion_delta, scroll_delta, hi_res_scroll) = devices.poll_pointer();
+        self.inputs
+            .for_each(|input| input.add_deltas(scroll_delta, motion_delta, hi_res_scroll));
+        let (scroll_delta, motion_delta, hi_res_scroll) = self.input.take();
+        let bits = devices.key_bits();
+        let pressed = |code| EvdevDevices::is_pressed(&bits, code);
+        // Same 1-based layout as X11.
+        let button_pressed = Some(false)
+            .into_iter()
+            .chain(MOUSE_BUTTONS.iter().map(|code| pressed(*code)))
+            .collect();
+        // The evdev coordinates don't belong to any screen.
+        MouseState {
+            coords,
+            button_pressed,
+            scroll_delta,
+            hi_res_scroll,
+            motion_delta,
+            monitor: None,
+            monitor_coords: coords,
+        }
+    }
+
+    fn query_evdev_keymap(devices: &EvdevDevices) -> Vec<Keycode> {
+        let bits = devices.key_bits();
+        // Skip the mouse, joystick and gamepad buttons found between the keys.
+        (1..kernel_key::BTN_MISC)
+            .chain(kernel_key::KEY_OK..kernel_key::BTN_TRIGGER_HAPPY)
+            .filter(|code| EvdevDevices::is_pressed(&bits, *code))
+            .map(kernel_key_to_keycode)
+            .collect()
+    }
+}
+
+fn kernel_key_to_keycode(kernel_code: u16) -> Keycode {
+    match kernel_code {
+        kernel_key::KEY_0 => Keycode::Key0,
+        kernel_key::KEY_1 => Keycode::Key1,
+        kernel_key::KEY_2 => Keycode::Key2,
+        kernel_key::KEY_3 => Keycode::Key3,
+        kernel_key::KEY_4 => Keycode::Key4,
+        kernel_key::KEY_5 => Keycode::Key5,
+        kernel_key::KEY_6 => Keycode::Key6,
+        kernel_key::KEY_7 => Keycode::Key7,
+        kernel_key::KEY_8 => Keycode::Key8,
+        kernel_key::KEY_9 => Keycode::Key9,
+        kernel_key::KEY_A => Keycode::A,
+        kernel_key::KEY_B => Keycode::B,
+        kernel_key::KEY_C => Keycode::C,
+        kernel_key::KEY_D => Keycode::D,
+        kernel_key::KEY_E => Keycode::E,
+        kernel_key::KEY_F => Keycode::F,
+        kernel_key::KEY_G => Keycode::G,
+        kernel_key::KEY_H => Keycode::H,
+        kernel_key::KEY_I => Keycode::I,
+        kernel_key::KEY_J => Keycode::J,
+        kernel_key::KEY_K => Keycode::K,
+        kernel_key::KEY_L => Keycode::L,
+        kernel_key::KEY_M => Keycode::M,
+        kernel_key::KEY_N => Keycode::N,
+        kernel_key::KEY_O => Keycode::O,
+        kernel_key::KEY_P => Keycode::P,
+        kernel_key::KEY_Q => Keycode::Q,
+        kernel_key::KEY_R => Keycode::R,
+        kernel_key::KEY_S => Keycode::S,
+        kernel_key::KEY_T => Keycode::T,
+        kernel_key::KEY_U => Keycode::U,
+        kernel_key::KEY_V => Keycode::V,
+        kernel_key::KEY_W => Keycode::W,
+        kernel_key::KEY_X => Keycode::X,
+        kernel_key::KEY_Y => Keycode::Y,
+        kernel_key::KEY_Z => Keycode::Z,
+        kernel_key::KEY_F1 => Keycode::F1,
+        kernel_key::KEY_F2 => Keycode::F2,
+        kernel_key::KEY_F3 => Keycode::F3,
+        kernel_key::KEY_F4 => Keycode::F4,
+        kernel_key::KEY_F5 => Keycode::F5,
+        kernel_key::KEY_F6 => Keycode::F6,
+        kernel_key::KEY_F7 => Keycode::F7,
+        kernel_key::KEY_F8 => Keycode::F8,
+        kernel_key::KEY_F9 => Keycode::F9,
+        kernel_key::KEY_F10 => Keycode::F10,
+        kernel_key::KEY_F11 => Keycode::F11,
+        kernel_key::KEY_F12 => Keycode::F12,
+        kernel_key::KEY_F13 => Keycode::F13,
+        kernel_key::KEY_F14 => Keycode::F14,
+        kernel_key::KEY_F15 => Keycode::F15,
+        kernel_key::KEY_F16 => Keycode::F16,
+        kernel_key::KEY_F17 => Keycode::F17,
+        kernel_key::KEY_F18 => Keycode::F18,
+        kernel_key::KEY_F19 => Keycode::F19,
+        kernel_key::KEY_F20 => Keycode::F20,
+        kernel_key::KEY_KP0 => Keycode::Numpad0,
+        kernel_key::KEY_KP1 => Keycode::Numpad1,
+        kernel_key::KEY_KP2 => Keycode::Numpad2,
+        kernel_key::KEY_KP3 => Keycode::Numpad3,
+        kernel_key::KEY_KP4 => Keycode::Numpad4,
+        kernel_key::KEY_KP5 => Keycode::Numpad5,
+        kernel_key::KEY_KP6 => Keycode::Numpad6,
+        kernel_key::KEY_KP7 => Keycode::Numpad7,
+        kernel_key::KEY_KP8 => Keycode::Numpad8,
+        kernel_key::KEY_KP9 => Keycode::Numpad9,
+        kernel_key::KEY_KPENTER => Keycode::NumpadEnter,
+        kernel_key::KEY_KPMINUS => Keycode::NumpadSubtract,
+        kernel_key::KEY_KPPLUS => Keycode::NumpadAdd,
+        kernel_key::KEY_KPSLASH => Keycode::NumpadDivide,
+        kernel_key::KEY_KPASTERISK => Keycode::NumpadMultiply,
+        kernel_key::KEY_KPEQUAL => Keycode::NumpadEquals,
+        kernel_key::KEY_KPDOT => Keycode::NumpadDecimal,
+        kernel_key::KEY_ESC => Keycode::Escape,
+        kernel_key::KEY_SPACE => Keycode::Space,
+        kernel_key::KEY_LEFTCTRL => Keycode::LControl,
+        kernel_key::KEY_RIGHTCTRL => Keycode::RControl,
+        kernel_key::KEY_LEFTSHIFT => Keycode::LShift,
+        kernel_key::KEY_RIGHTSHIFT => Keycode::RShift,
+        kernel_key::KEY_LEFTALT => Keycode::LAlt,
+        kernel_key::KEY_RIGHTALT => Keycode::RAlt,
+        kernel_key::KEY_LEFTMETA => Keycode::LMeta,
+        kernel_key::KEY_RIGHTMETA => Keycode::RMeta,
+        kernel_key::KEY_ENTER => Keycode::Enter,
+        kernel_key::KEY_UP => Keycode::Up,
+        kernel_key::KEY_DOWN => Keycode::Down,
+        kernel_key::KEY_LEFT => Keycode::Left,
+        kernel_key::KEY_RIGHT => Keycode::Right,
+        kernel_key::KEY_BACKSPACE => Keycode::Backspace,
+        kernel_key::KEY_CAPSLOCK => Keycode::CapsLock,
+        kernel_key::KEY_TAB => Keycode::Tab,
+        kernel_key::KEY_HOME => Keycode::Home,
+        kernel_key::KEY_END => Keycode::End,
+        kernel_key::KEY_PAGEUP => Keycode::PageUp,
+        kernel_key::KEY_PAGEDOWN => Keycode::PageDown,
+        kernel_key::KEY_INSERT => Keycode::Insert,
+        kernel_key::KEY_DELETE => Keycode::Delete,
+        kernel_key::KEY_GRAVE => Keycode::Grave,
+        kernel_key::KEY_MINUS => Keycode::Minus,
+        kernel_key::KEY_EQUAL => Keycode::Equal,
+        kernel_key::KEY_LEFTBRACE => Keycode::LeftBracket,
+        kernel_key::KEY_RIGHTBRACE => Keycode::RightBracket,
+        kernel_key::KEY_BACKSLASH => Keycode::BackSlash,
+        kernel_key::KEY_SEMICOLON => Keycode::Semicolon,
+        kernel_key::KEY_APOSTROPHE => Keycode::Apostrophe,
+        kernel_key::KEY_COMMA => Keycode::Comma,
+        kernel_key::KEY_DOT => Keycode::Dot,
+        kernel_key::KEY_SLASH => Keycode::Slash,
+        kernel_key::KEY_F21 => Keycode::F21,
+        kernel_key::KEY_F22 => Keycode::F22,
+        kernel_key::KEY_F23 => Keycode::F23,
+        kernel_key::KEY_F24 => Keycode::F24,
+        kernel_key::KEY_SYSRQ => Keycode::PrintScreen,
+        kernel_key::KEY_SCROLLLOCK => Keycode::ScrollLock,
+        kernel_key::KEY_PAUSE => Keycode::Pause,
+        kernel_key::KEY_COMPOSE => Keycode::Menu,
+        kernel_key::KEY_NUMLOCK => Keycode::NumLock,
+        kernel_key::KEY_KPCOMMA => Keycode::NumpadComma,
+        kernel_key::KEY_KPJPCOMMA => Keycode::NumpadJpComma,
+        kernel_key::KEY_KPPLUSMINUS => Keycode::NumpadPlusMinus,
+        kernel_key::KEY_KPLEFTPAREN => Keycode::NumpadLeftParen,
+        kernel_key::KEY_KPRIGHTPAREN => Keycode::NumpadRightParen,
+        kernel_key::KEY_102ND => Keycode::IntlBackslash,
+        kernel_key::KEY_RO => Keycode::Ro,
+        kernel_key::KEY_YEN => Keycode::Yen,
+        kernel_key::KEY_ZENKAKUHANKAKU => Keycode::Zenkakuhankaku,
+        kernel_key::KEY_KATAKANA => Keycode::Katakana,
+        kernel_key::KEY_HIRAGANA => Keycode::Hiragana,
+        kernel_key::KEY_KATAKANAHIRAGANA => Keycode::KatakanaHiragana,
+        kernel_key::KEY_HENKAN => Keycode::Henkan,
+        kernel_key::KEY_MUHENKAN => Keycode::Muhenkan,
+        kernel_key::KEY_HANGEUL => Keycode::Hangeul,
+        kernel_key::KEY_HANJA => Keycode::Hanja,
+        kernel_key::KEY_MUTE => Keycode::Mute,
+        kernel_key::KEY_VOLUMEDOWN => Keycode::VolumeDown,
+        kernel_key::KEY_VOLUMEUP => Keycode::VolumeUp,
+        kernel_key::KEY_MICMUTE => Keycode::MicMute,
+        kernel_key::KEY_PLAYPAUSE => Keycode::MediaPlayPause,
+        kernel_key::KEY_PLAYCD => Keycode::MediaPlay,
+        kernel_key::KEY_PAUSECD => Keycode::MediaPause,
+        kernel_key::KEY_STOPCD => Keycode::MediaStop,
+        kernel_key::KEY_PREVIOUSSONG => Keycode::MediaPrevious,
+        kernel_key::KEY_NEXTSONG => Keycode::MediaNext,
+        kernel_key::KEY_REWIND => Keycode::MediaRewind,
+        kernel_key::KEY_FASTFORWARD => Keycode::MediaFastForward,
+        kernel_key::KEY_RECORD => Keycode::MediaRecord,
+        kernel_key::KEY_EJECTCD => Keycode::Eject,
+        kernel_key::KEY_BACK => Keycode::BrowserBack,
+        kernel_key::KEY_FORWARD => Keycode::BrowserForward,
+        kernel_key::KEY_REFRESH => Keycode::BrowserRefresh,
+        kernel_key::KEY_STOP => Keycode::BrowserStop,
+        kernel_key::KEY_SEARCH => Keycode::BrowserSearch,
+        kernel_key::KEY_BOOKMARKS => Keycode::BrowserFavorites,
+        kernel_key::KEY_HOMEPAGE => Keycode::BrowserHome,
+        kernel_key::KEY_MAIL => Keycode::LaunchMail,
+        kernel_key::KEY_MEDIA => Keycode::LaunchMediaPlayer,
+        kernel_key::KEY_COMPUTER => Keycode::LaunchMyComputer,
+        kernel_key::KEY_CALC => Keycode::LaunchCalculator,
+        kernel_key::KEY_POWER => Keycode::Power,
+        kernel_key::KEY_SLEEP => Keycode::Sleep,
+        kernel_key::KEY_WAKEUP => Keycode::WakeUp,
+        kernel_key::KEY_BRIGHTNESSDOWN => Keycode::BrightnessDown,
+        kernel_key::KEY_BRIGHTNESSUP => Keycode::BrightnessUp,
+        kernel_key::KEY_HELP => Keycode::Help,
+        kernel_key::KEY_PROPS => Keycode::Props,
+        kernel_key::KEY_FRONT => Keycode::Front,
+        kernel_key::KEY_OPEN => Keycode::Open,
+        kernel_key::KEY_FIND => Keycode::Find,
+        kernel_key::KEY_AGAIN => Keycode::Again,
+        kernel_key::KEY_UNDO => Keycode::Undo,
+        kernel_key::KEY_REDO => Keycode::Redo,
+        kernel_key::KEY_CUT => Keycode::Cut,
+        kernel_key::KEY_COPY => Keycode::Copy,
+        kernel_key::KEY_PASTE => Keycode::Paste,
+        _ => Keycode::Unknown(kernel_code),
+    }
+}
+
+fn keycode_to_kernel_key(keycode: Keycode) -> Option<u16> {
+    match keycode {
+        Keycode::Key0 => Some(kernel_key::KEY_0),
+        Keycode::Key1 => Some(kernel_key::KEY_1),
+        Keycode::Key2 => Some(kernel_key::KEY_2),
+        Keycode::Key3 => Some(kernel_key::KEY_3),
+        Keycode::Key4 => Some(kernel_key::KEY_4),
+        Keycode::Key5 => Some(kernel_key::KEY_5),
+        Keycode::Key6 => Some(kernel_key::KEY_6),
+        Keycode::Key7 => Some(kernel_key::KEY_7),
+        Keycode::Key8 => Some(kernel_key::KEY_8),
+        Keycode::Key9 => Some(kernel_key::KEY_9),
+        Keycode::A => Some(kernel_key::KEY_A),
+        Keycode::B => Some(kernel_key::KEY_B),
+        Keycode::C => Some(kernel_key::KEY_C),
+        Keycode::D => Some(kernel_key::KEY_D),
+        Keycode::E => Some(kernel_key::KEY_E),
+        Keycode::F => Some(kernel_key::KEY_F),
+        Keycode::G => Some(kernel_key::KEY_G),
+        Keycode::H => Some(kernel_key::KEY_H),
+        Keycode::I => Some(kernel_key::KEY_I),
+        Keycode::J => Some(kernel_key::KEY_J),
+        Keycode::K => Some(kernel_key::KEY_K),
+        Keycode::L => Some(kernel_key::KEY_L),
+        Keycode::M => Some(kernel_key::KEY_M),
+        Keycode::N => Some(kernel_key::KEY_N),
+        Keycode::O => Some(kernel_key::KEY_O),
+        Keycode::P => Some(kernel_key::KEY_P),
+        Keycode::Q => Some(kernel_key::KEY_Q),
+        Keycode::R => Some(kernel_key::KEY_R),
+        Keycode::S => Some(kernel_key::KEY_S),
+        Keycode::T => Some(kernel_key::KEY_T),
+        Keycode::U => Some(kernel_key::KEY_U),
+        Keycode::V => Some(kernel_key::KEY_V),
+        Keycode::W => Some(kernel_key::KEY_W),
+        Keycode::X => Some(kernel_key::KEY_X),
+        Keycode::Y => Some(kernel_key::KEY_Y),
+        Keycode::Z => Some(kernel_key::KEY_Z),
+        Keycode::F1 => Some(kernel_key::KEY_F1),
+        Keycode::F2 => Some(kernel_key::KEY_F2),
+        Keycode::F3 => Some(kernel_key::KEY_F3),
+        Keycode::F4 => Some(kernel_key::KEY_F4),
+        Keycode::F5 => Some(kernel_key::KEY_F5),
+        Keycode::F6 => Some(kernel_key::KEY_F6),
+        Keycode::F7 => Some(kernel_key::KEY_F7),
+        Keycode::F8 => Some(kernel_key::KEY_F8),
+        Keycode::F9 => Some(kernel_key::KEY_F9),
+        Keycode::F10 => Some(kernel_key::KEY_F10),
+        Keycode::F11 => Some(kernel_key::KEY_F11),
+        Keycode::F12 => Some(kernel_key::KEY_F12),
+        Keycode::F13 => Some(kernel_key::KEY_F13),
+        Keycode::F14 => Some(kernel_key::KEY_F14),
+        Keycode::F15 => Some(kernel_key::KEY_F15),
+        Keycode::F16 => Some(kernel_key::KEY_F16),
+        Keycode::F17 => Some(kernel_key::KEY_F17),
+        Keycode::F18 => Some(kernel_key::KEY_F18),
+        Keycode::F19 => Some(kernel_key::KEY_F19),
+        Keycode::F20 => Some(kernel_key::KEY_F20),
+        Keycode::Numpad0 => Some(kernel_key::KEY_KP0),
+        Keycode::Numpad1 => Some(kernel_key::KEY_KP1),
+        Keycode::Numpad2 => Some(kernel_key::KEY_KP2),
+        Keycode::Numpad3 => Some(kernel_key::KEY_KP3),
+        Keycode::Numpad4 => Some(kernel_key::KEY_KP4),
+        Keycode::Numpad5 => Some(kernel_key::KEY_KP5),
+        Keycode::Numpad6 => Some(kernel_key::KEY_KP6),
+        Keycode::Numpad7 => Some(kernel_key::KEY_KP7),
+        Keycode::Numpad8 => Some(kernel_key::KEY_KP8),
+        Keycode::Numpad9 => Some(kernel_key::KEY_KP9),
+        Keycode::NumpadEnter => Some(kernel_key::KEY_KPENTER),
+        Keycode::NumpadSubtract => Some(kernel_key::KEY_KPMINUS),
+        Keycode::NumpadAdd => Some(kernel_key::KEY_KPPLUS),
+        Keycode::NumpadDivide => Some(kernel_key::KEY_KPSLASH),
+        Keycode::NumpadMultiply => Some(kernel_key::KEY_KPASTERISK),
+        Keycode::NumpadEquals => Some(kernel_key::KEY_KPEQUAL),
+        Keycode::NumpadDecimal => Some(kernel_key::KEY_KPDOT),
+        Keycode::Escape => Some(kernel_key::KEY_ESC),
+        Keycode::Space => Some(kernel_key::KEY_SPACE),
+        Keycode::LControl => Some(kernel_key::KEY_LEFTCTRL),
+        Keycode::RControl => Some(kernel_key::KEY_RIGHTCTRL),
+        Keycode::LShift => Some(kernel_key::KEY_LEFTSHIFT),
+        Keycode::RShift => Some(kernel_key::KEY_RIGHTSHIFT),
+        Keycode::LAlt => Some(kernel_key::KEY_LEFTALT),
+        Keycode::RAlt => Some(kernel_key::KEY_RIGHTALT),
+        Keycode::LMeta => Some(kernel_key::KEY_LEFTMETA),
+        Keycode::RMeta => Some(kernel_key::KEY_RIGHTMETA),
+        Keycode::Enter => Some(kernel_key::KEY_ENTER),
+        Keycode::Up => Some(kernel_key::KEY_UP),
+        Keycode::Down => Some(kernel_key::KEY_DOWN),
+        Keycode::Left => Some(kernel_key::KEY_LEFT),
+        Keycode::Right => Some(kernel_key::KEY_RIGHT),
+        Keycode::Backspace => Some(kernel_key::KEY_BACKSPACE),
+        Keycode::CapsLock => Some(kernel_key::KEY_CAPSLOCK),
+        Keycode::Tab => Some(kernel_key::KEY_TAB),
+        Keycode::Home => Some(kernel_key::KEY_HOME),
+        Keycode::End => Some(kernel_key::KEY_END),
+        Keycode::PageUp => Some(kernel_key::KEY_PAGEUP),
+        Keycode::PageDown => Some(kernel_key::KEY_PAGEDOWN),
+        Keycode::Insert => Some(kernel_key::KEY_INSERT),
+        Keycode::Delete => Some(kernel_key::KEY_DELETE),
+        Keycode::Grave => Some(kernel_key::KEY_GRAVE),
+        Keycode::Minus => Some(kernel_key::KEY_MINUS),
+        Keycode::Equal => Some(kernel_key::KEY_EQUAL),
+        Keycode::LeftBracket => Some(kernel_key::KEY_LEFTBRACE),
+        Keycode::RightBracket => Some(kernel_key::KEY_RIGHTBRACE),
+        Keycode::BackSlash => Some(kernel_key::KEY_BACKSLASH),
+        Keycode::Semicolon => Some(kernel_key::KEY_SEMICOLON),
+        Keycode::Apostrophe => Some(kernel_key::KEY_APOSTROPHE),
+        Keycode::Comma => Some(kernel_key::KEY_COMMA),
+        Keycode::Dot => Some(kernel_key::KEY_DOT),
+        Keycode::Slash => Some(kernel_key::KEY_SLASH),
+        Keycode::F21 => Some(kernel_key::KEY_F21),
+        Keycode::F22 => Some(kernel_key::KEY_F22),
+        Keycode::F23 => Some(kernel_key::KEY_F23),
+        Keycode::F24 => Some(kernel_key::KEY_F24),
+        Keycode::PrintScreen => Some(kernel_key::KEY_SYSRQ),
+        Keycode::ScrollLock => Some(kernel_key::KEY_SCROLLLOCK),
+        Keycode::Pause => Some(kernel_key::KEY_PAUSE),
+        Keycode::Menu => Some(kernel_key::KEY_COMPOSE),
+        Keycode::NumLock => Some(kernel_key::KEY_NUMLOCK),
+        Keycode::NumpadComma => Some(kernel_key::KEY_KPCOMMA),
+        Keycode::NumpadJpComma => Some(kernel_key::KEY_KPJPCOMMA),
+        Keycode::NumpadPlusMinus => Some(kernel_key::KEY_KPPLUSMINUS),
+        Keycode::NumpadLeftParen => Some(kernel_key::KEY_KPLEFTPAREN),
+        Keycode::NumpadRightParen => Some(kernel_key::KEY_KPRIGHTPAREN),
+        Keycode::IntlBackslash => Some(kernel_key::KEY_102ND),
+        Keycode::Ro => Some(kernel_key::KEY_RO),
+        Keycode::Yen => Some(kernel_key::KEY_YEN),
+        Keycode::Zenkakuhankaku => Some(kernel_key::KEY_ZENKAKUHANKAKU),
+        Keycode::Katakana => Some(kernel_key::KEY_KATAKANA),
+        Keycode::Hiragana => Some(kernel_key::KEY_HIRAGANA),
+        Keycode::KatakanaHiragana => Some(kernel_key::KEY_KATAKANAHIRAGANA),
+        Keycode::Henkan => Some(kernel_key::KEY_HENKAN),
+        Keycode::Muhenkan => Some(kernel_key::KEY_MUHENKAN),
+        Keycode::Hangeul => Some(kernel_key::KEY_HANGEUL),
+        Keycode::Hanja => Some(kernel_key::KEY_HANJA),
+        Keycode::Mute => Some(kernel_key::KEY_MUTE),
+        Keycode::VolumeDown => Some(kernel_key::KEY_VOLUMEDOWN),
+        Keycode::VolumeUp => Some(kernel_key::KEY_VOLUMEUP),
+        Keycode::MicMute => Some(kernel_key::KEY_MICMUTE),
+        Keycode::MediaPlayPause => Some(kernel_key::KEY_PLAYPAUSE),
+        Keycode::MediaPlay => Some(kernel_key::KEY_PLAYCD),
+        Keycode::MediaPause => Some(kernel_key::KEY_PAUSECD),
+        Keycode::MediaStop => Some(kernel_key::KEY_STOPCD),
+        Keycode::MediaPrevious => Some(kernel_key::KEY_PREVIOUSSONG),
+        Keycode::MediaNext => Some(kernel_key::KEY_NEXTSONG),
+        Keycode::MediaRewind => Some(kernel_key::KEY_REWIND),
+        Keycode::MediaFastForward => Some(kernel_key::KEY_FASTFORWARD),
+        Keycode::MediaRecord => Some(kernel_key::KEY_RECORD),
+        Keycode::Eject => Some(kernel_key::KEY_EJECTCD),
+        Keycode::BrowserBack => Some(kernel_key::KEY_BACK),
+        Keycode::BrowserForward => Some(kernel_key::KEY_FORWARD),
+        Keycode::BrowserRefresh => Some(kernel_key::KEY_REFRESH),
+        Keycode::BrowserStop => Some(kernel_key::KEY_STOP),
+        Keycode::BrowserSearch => Some(kernel_key::KEY_SEARCH),
+        Keycode::BrowserFavorites => Some(kernel_key::KEY_BOOKMARKS),
+        Keycode::BrowserHome => Some(kernel_key::KEY_HOMEPAGE),
+        Keycode::LaunchMail => Some(kernel_key::KEY_MAIL),
+        Keycode::LaunchMediaPlayer => Some(kernel_key::KEY_MEDIA),
+        Keycode::LaunchMyComputer => Some(kernel_key::KEY_COMPUTER),
+        Keycode::LaunchCalculator => Some(kernel_key::KEY_CALC),
+        Keycode::Power => Some(kernel_key::KEY_POWER),
+        Keycode::Sleep => Some(kernel_key::KEY_SLEEP),
+        Keycode::WakeUp => Some(kernel_key::KEY_WAKEUP),
+        Keycode::BrightnessDown => Some(kernel_key::KEY_BRIGHTNESSDOWN),
+        Keycode::BrightnessUp => Some(kernel_key::KEY_BRIGHTNESSUP),
+        Keycode::Help => Some(kernel_key::KEY_HELP),
+        Keycode::Props => Some(kernel_key::KEY_PROPS),
+        Keycode::Front => Some(kernel_key::KEY_FRONT),
+        Keycode::Open => Some(kernel_key::KEY_OPEN),
+        Keycode::Find => Some(kernel_key::KEY_FIND),
+        Keycode::Again => Some(kernel_key::KEY_AGAIN),
+        Keycode::Undo => Some(kernel_key::KEY_UNDO),
+        Keycode::Redo => Some(kernel_key::KEY_REDO),
+        Keycode::Cut => Some(kernel_key::KEY_CUT),
+        Keycode::Copy => Some(kernel_key::KEY_COPY),
+        Keycode::Paste => Some(kernel_key::KEY_PASTE),
+        Keycode::Unknown(kernel_code) => Some(kernel_code),
+        // macOS only keys.
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kernel_key_to_keycode, keycode_to_kernel_key};
+    use keymap::Keycode;
+
+    #[test]
+    fn kernel_keycodes_round_trip() {
+        let unknown = Keycode::Unknown(0x2f0);
+        for keycode in Keycode::iter().chain(Some(unknown)) {
+            if let Some(kernel_key) = keycode_to_kernel_key(keycode) {
+                assert_eq!(kernel_key_to_keycode(kernel_key), keycode);
+            }
         }
     }
 }
@@ -0,0 +1,272 @@
+//! Input synthesis with a uinput virtual device.
+
+extern crate libc;
+
+use super::{kernel_key, keycode_to_kernel_key, MOUSE_BUTTONS};
+use error::Error;
+use keymap::Keycode;
+use mouse_state::{MouseButton, MousePosition, ScrollDelta};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::os::raw::c_char;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::slice;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+
+const SYN_REPORT: u16 = 0x00;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// Equivalent of the kernel `_IOC(dir, 'U', nr, size)` macro.
+fn uiioc(dir: libc::c_ulong, nr: u32, size: usize) -> libc::c_ulong {
+    (dir << 30)
+        | ((size as libc::c_ulong) << 16)
+        | ((b'U' as libc::c_ulong) << 8)
+        | nr as libc::c_ulong
+}
+
+/// `_IO('U', nr)`.
+fn uiio(nr: u32) -> libc::c_ulong {
+    uiioc(0, nr, 0)
+}
+
+/// `_IOW('U', nr, T)`.
+fn uiiow<T>(nr: u32) -> libc::c_ulong {
+    uiioc(1, nr, mem::size_of::<T>())
+}
+
+fn ui_dev_create() -> libc::c_ulong {
+    uiio(1)
+}
+
+fn ui_dev_destroy() -> libc::c_ulong {
+    uiio(2)
+}
+
+fn ui_dev_setup() -> libc::c_ulong {
+    uiiow::<libc::uinput_setup>(3)
+}
+
+fn ui_abs_setup() -> libc::c_ulong {
+    uiiow::<libc::uinput_abs_setup>(4)
+}
+
+fn ui_set_evbit() -> libc::c_ulong {
+    uiiow::<libc::c_int>(100)
+}
+
+/// `UI_SET_KEYBIT`, `UI_SET_RELBIT` or `UI_SET_ABSBIT`, enabling a code of the event type.
+fn ui_set_codebit(ev: u16) -> libc::c_ulong {
+    match ev {
+        EV_KEY => uiiow::<libc::c_int>(101),
+        EV_REL => uiiow::<libc::c_int>(102),
+        _ => uiiow::<libc::c_int>(103),
+    }
+}
+
+fn uinput_error(error: io::Error) -> Error {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => Error::PermissionDenied,
+        _ => Error::BackendUnavailable(format!("uinput: {}", error)),
+    }
+}
+
+/// A device created through `/dev/uinput`, removed when dropped.
+#[derive(Debug)]
+struct UinputDevice {
+    file: File,
+}
+
+impl UinputDevice {
+    /// Creates a device sending the given codes of each event type, with the absolute
+    /// axes ranging from 0 to the given maximum.
+    fn create(
+        name: &str,
+        keys: &[u16],
+        rel: &[u16],
+        abs: &[(u16, i32)],
+    ) -> Result<UinputDevice, Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open("/dev/uinput")
+            .map_err(uinput_error)?;
+        let device = UinputDevice { file };
+        device.ioctl(ui_set_evbit(), EV_SYN as libc::c_ulong)?;
+        for (ev, codes) in [
+            (EV_KEY, keys.to_vec()),
+            (EV_REL, rel.to_vec()),
+            (EV_ABS, abs.iter().map(|(code, _)| *code).collect()),
+        ] {
+            if codes.is_empty() {
+                continue;
+            }
+            device.ioctl(ui_set_evbit(), ev as libc::c_ulong)?;
+            for code in codes {
+                device.ioctl(ui_set_codebit(ev), code as libc::c_ulong)?;
+            }
+        }
+        for &(code, maximum) in abs {
+            let mut setup: libc::uinput_abs_setup = unsafe { mem::zeroed() };
+            setup.code = code;
+            setup.absinfo.maximum = maximum;
+            device.ioctl(ui_abs_setup(), &setup as *const _ as libc::c_ulong)?;
+        }
+        let mut setup: libc::uinput_setup = unsafe { mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        for (dst, src) in setup.name.iter_mut().zip(name.bytes()) {
+            *dst = src as c_char;
+        }
+        device.ioctl(ui_dev_setup(), &setup as *const _ as libc::c_ulong)?;
+        device.ioctl(ui_dev_create(), 0)?;
+        Ok(device)
+    }
+
+    fn ioctl(&self, request: libc::c_ulong, arg: libc::c_ulong) -> Result<(), Error> {
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, arg) } < 0 {
+            return Err(uinput_error(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Sends the events followed by a `SYN_REPORT`, in a single write so that reports
+    /// sent from several threads don't interleave.
+    fn send(&self, events: &[(u16, u16, i32)]) -> Result<(), Error> {
+        let events: Vec<libc::input_event> = events
+            .iter()
+            .chain(Some(&(EV_SYN, SYN_REPORT, 0)))
+            .map(|&(type_, code, value)| {
+                let mut event: libc::input_event = unsafe { mem::zeroed() };
+                event.type_ = type_;
+                event.code = code;
+                event.value = value;
+                event
+            })
+            .collect();
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                events.as_ptr() as *const u8,
+                events.len() * mem::size_of::<libc::input_event>(),
+            )
+        };
+        (&self.file).write_all(bytes).map_err(uinput_error)
+    }
+}
+
+impl Drop for UinputDevice {
+    fn drop(&mut self) {
+        let _ = self.ioctl(ui_dev_destroy(), 0);
+    }
+}
+
+/// A virtual keyboard and mouse created with Linux uinput, to synthesize input where
+/// XTest isn't available, such as on Wayland or in a console. It is removed when dropped.
+///
+/// The process needs write access to `/dev/uinput`. The desktop may take a moment to
+/// pick the device up after its creation, and ignore the events sent in the meantime.
+///
+/// Relative motion is always supported. Absolute motion, through
+/// [`DeviceControl::mouse_move_to`](crate::DeviceControl::mouse_move_to), needs to know
+/// the screen size, see [`VirtualDevice::with_screen_size`].
+///
+/// ```no_run
+/// use device_query::{DeviceControl, Keycode, VirtualDevice};
+///
+/// let device = VirtualDevice::new().unwrap();
+/// device.key_down(Keycode::A).unwrap();
+/// device.key_up(Keycode::A).unwrap();
+/// device.mouse_move_by(10, -10).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct VirtualDevice {
+    device: UinputDevice,
+    /// A second device with absolute axes, as the desktop wouldn't handle a device
+    /// moving both relatively and absolutely.
+    pointer: Option<UinputDevice>,
+}
+
+impl VirtualDevice {
+    /// Creates a virtual keyboard and mouse, moving relatively.
+    pub fn new() -> Result<VirtualDevice, Error> {
+        let keys: Vec<u16> = (1..kernel_key::BTN_MISC)
+            .chain(kernel_key::KEY_OK..kernel_key::BTN_TRIGGER_HAPPY)
+            .chain(MOUSE_BUTTONS.iter().copied())
+            .collect();
+        let device = UinputDevice::create(
+            "device_query virtual device",
+            &keys,
+            &[REL_X, REL_Y, REL_WHEEL, REL_HWHEEL],
+            &[],
+        )?;
+        Ok(VirtualDevice {
+            device,
+            pointer: None,
+        })
+    }
+
+    /// Creates a virtual keyboard and mouse, also able to move to absolute positions of
+    /// a screen of the given size in pixels.
+    pub fn with_screen_size(width: i32, height: i32) -> Result<VirtualDevice, Error> {
+        let mut device = Self::new()?;
+        device.pointer = Some(UinputDevice::create(
+            "device_query virtual pointer",
+            &MOUSE_BUTTONS,
+            &[],
+            &[(ABS_X, width - 1), (ABS_Y, height - 1)],
+        )?);
+        Ok(device)
+    }
+
+    /// Move the mouse relatively to its current position.
+    pub fn mouse_move_by(&self, dx: i32, dy: i32) -> Result<(), Error> {
+        self.device
+            .send(&[(EV_REL, REL_X, dx), (EV_REL, REL_Y, dy)])
+    }
+
+    /// Press or release a key.
+    pub fn send_key(&self, key: Keycode, pressed: bool) -> Result<(), Error> {
+        let code = keycode_to_kernel_key(key)
+            .ok_or_else(|| Error::UnsupportedInput(format!("no kernel keycode for {}", key)))?;
+        self.device.send(&[(EV_KEY, code, pressed as i32)])
+    }
+
+    /// Move the mouse to a position of the screen.
+    pub fn send_mouse_move(&self, (x, y): MousePosition) -> Result<(), Error> {
+        let pointer = self.pointer.as_ref().ok_or_else(|| {
+            Error::UnsupportedInput("absolute motion needs the screen size".to_string())
+        })?;
+        pointer.send(&[(EV_ABS, ABS_X, x), (EV_ABS, ABS_Y, y)])
+    }
+
+    /// Press or release a mouse button.
+    pub fn send_mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        let code = button
+            .number()
+            .checked_sub(1)
+            .and_then(|index| MOUSE_BUTTONS.get(index))
+            .ok_or_else(|| Error::UnsupportedInput(format!("mouse button {}", button)))?;
+        self.device.send(&[(EV_KEY, *code, pressed as i32)])
+    }
+
+    /// Scroll the mouse wheel.
+    pub fn send_scroll(&self, delta: ScrollDelta) -> Result<(), Error> {
+        self.device.send(&[
+            (EV_REL, REL_WHEEL, delta.vertical),
+            (EV_REL, REL_HWHEEL, delta.horizontal),
+        ])
+    }
+}
@@ -0,0 +1,517 @@
+//! XInput2 raw event listener.
+//!
+//! Raw events are delivered for every key press, button press and motion of the
+//! physical devices, regardless of which window has the focus, so the event loop
+//! can react to them as they happen instead of polling the X server.
+
+extern crate libc;
+
+use super::x11::{xinput2, xlib};
+use super::{kernel_key_to_keycode, keycode_to_kernel_key, DeviceState, X11Connection};
+use error::Error;
+use keymap::Keycode;
+use modifiers::Modifiers;
+use mouse_state::{HiResScroll, MouseDelta, MousePosition, ScrollSource};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::mem;
+use std::slice;
+use std::time::{Duration, Instant};
+
+/// An input event read from the X server.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RawEvent {
+    KeyDown(Keycode),
+    KeyUp(Keycode),
+    /// X11 button number, 4 to 7 being the scroll wheel, see `mouse_button_from_x11`.
+    ButtonDown(u32),
+    ButtonUp(u32),
+    /// Unaccelerated relative motion, which may be zero when the device moved by less
+    /// than a unit.
+    Motion(MouseDelta),
+    /// Motion of an absolute device, such as a tablet or the XTest pointer, whose
+    /// valuators are coordinates rather than a delta: only the position changed.
+    Moved,
+    /// High-resolution scrolling.
+    Scroll(HiResScroll),
+}
+
+/// Converts X server timestamps to instants.
+///
+/// The server time is in milliseconds from an unknown origin, so it is anchored to the
+/// instant an event is received. The anchor moves back to the latest event received as
+/// soon as it was delivered faster, so the times converge to the least delayed event.
+#[derive(Default)]
+struct ServerClock {
+    anchor: Option<(u32, Instant)>,
+}
+
+impl ServerClock {
+    fn instant(&mut self, time: xlib::Time, received: Instant) -> Instant {
+        // The server time is a 32 bits counter wrapping around.
+        let time = time as u32;
+        if let Some((anchor_time, anchor)) = self.anchor {
+            let elapsed = Duration::from_millis(time.wrapping_sub(anchor_time) as u64);
+            if let Some(instant) = anchor.checked_add(elapsed) {
+                if instant <= received {
+                    return instant;
+                }
+            }
+        }
+        self.anchor = Some((time, received));
+        received
+    }
+}
+
+/// Access to the functions of `libXi`, once the server was checked to support XInput 2.
+#[derive(Debug)]
+pub(super) struct XInput {
+    opcode: i32,
+}
+
+impl XInput {
+    pub fn open(xc: &X11Connection) -> Result<XInput, Error> {
+        let unsupported = || Error::BackendUnavailable("XInput 2 is not supported".to_string());
+        let name = CString::new("XInputExtension").unwrap();
+        let mut opcode = 0;
+        let mut event = 0;
+        let mut error = 0;
+        unsafe {
+            if xlib::XQueryExtension(
+                xc.display,
+                name.as_ptr(),
+                &mut opcode,
+                &mut event,
+                &mut error,
+            ) == 0
+            {
+                return Err(unsupported());
+            }
+            // 2.1 for the smooth scrolling valuators.
+            let mut major = 2;
+            let mut minor = 1;
+            if xinput2::XIQueryVersion(xc.display, &mut major, &mut minor) != 0 {
+                return Err(unsupported());
+            }
+        }
+        Ok(XInput { opcode })
+    }
+
+    /// Selects the raw events of the master devices on the root window.
+    fn select_raw_events(&self, xc: &X11Connection) {
+        let mut mask = [0u8; xinput2::XI_LASTEVENT as usize / 8 + 1];
+        for event in [
+            xinput2::XI_RawKeyPress,
+            xinput2::XI_RawKeyRelease,
+            xinput2::XI_RawButtonPress,
+            xinput2::XI_RawButtonRelease,
+            xinput2::XI_RawMotion,
+        ] {
+            xinput2::XISetMask(&mut mask, event);
+        }
+        // Devices being added or removed, as their ids are reused.
+        let mut hierarchy_mask = [0u8; xinput2::XI_LASTEVENT as usize / 8 + 1];
+        xinput2::XISetMask(&mut hierarchy_mask, xinput2::XI_HierarchyChanged);
+        let mut event_masks = [
+            xinput2::XIEventMask {
+                deviceid: xinput2::XIAllMasterDevices,
+                mask_len: mask.len() as i32,
+                mask: mask.as_mut_ptr(),
+            },
+            xinput2::XIEventMask {
+                deviceid: xinput2::XIAllDevices,
+                mask_len: hierarchy_mask.len() as i32,
+                mask: hierarchy_mask.as_mut_ptr(),
+            },
+        ];
+        unsafe {
+            let root = xlib::XDefaultRootWindow(xc.display);
+            xinput2::XISelectEvents(
+                xc.display,
+                root,
+                event_masks.as_mut_ptr(),
+                event_masks.len() as i32,
+            );
+            xlib::XFlush(xc.display);
+        }
+    }
+
+    /// Returns the smooth scrolling valuators of a slave device and the mode of its axes.
+    fn slave_device(&self, xc: &X11Connection, deviceid: i32) -> SlaveDevice {
+        let display = xc.display;
+        let mut device = SlaveDevice {
+            valuators: vec![],
+            source: Self::scroll_source(xc, deviceid),
+            absolute: false,
+        };
+        unsafe {
+            let mut count = 0;
+            let info = xinput2::XIQueryDevice(display, deviceid, &mut count);
+            if info.is_null() {
+                return device;
+            }
+            if count > 0 {
+                let info = &*info;
+                let classes = slice::from_raw_parts(info.classes, info.num_classes as usize);
+                let mut absolute_axes = 0;
+                for class in classes {
+                    if (**class)._type == xinput2::XIValuatorClass {
+                        let class = &*(*class as *const xinput2::XIValuatorClassInfo);
+                        if class.number <= 1 && class.mode == xinput2::XIModeAbsolute {
+                            absolute_axes += 1;
+                        }
+                        continue;
+                    }
+                    if (**class)._type != xinput2::XIScrollClass {
+                        continue;
+                    }
+                    let class = &*(*class as *const xinput2::XIScrollClassInfo);
+                    if class.increment != 0.0 {
+                        device.valuators.push(ScrollValuator {
+                            number: class.number,
+                            vertical: class.scroll_type == xinput2::XIScrollTypeVertical,
+                            increment: class.increment,
+                        });
+                    }
+                }
+                device.absolute = absolute_axes == 2;
+            }
+            xinput2::XIFreeDeviceInfo(info);
+        }
+        device
+    }
+
+    /// Touchpads are told apart by the scroll methods the libinput driver offers for
+    /// them: two fingers or the edge, where wheels only have their wheel.
+    fn scroll_source(xc: &X11Connection, deviceid: i32) -> ScrollSource {
+        let name = CString::new("libinput Scroll Methods Available").unwrap();
+        let mut source = ScrollSource::Wheel;
+        unsafe {
+            let property = xlib::XInternAtom(xc.display, name.as_ptr(), xlib::True);
+            if property == 0 {
+                return source;
+            }
+            let mut type_return = 0;
+            let mut format_return = 0;
+            let mut items = 0;
+            let mut bytes_after = 0;
+            let mut data = std::ptr::null_mut();
+            if xinput2::XIGetProperty(
+                xc.display,
+                deviceid,
+                property,
+                0,
+                2,
+                xlib::False,
+                0, // AnyPropertyType
+                &mut type_return,
+                &mut format_return,
+                &mut items,
+                &mut bytes_after,
+                &mut data,
+            ) != 0
+            {
+                return source;
+            }
+            if !data.is_null() {
+                // Two fingers, then edge scrolling.
+                let methods = slice::from_raw_parts(data, items as usize);
+                if format_return == 8 && methods.iter().any(|method| *method != 0) {
+                    source = ScrollSource::Continuous;
+                }
+                xlib::XFree(data as *mut _);
+            }
+        }
+        source
+    }
+
+    /// Returns the X11 numbers of the buttons pressed on the client pointer. Unlike the
+    /// core protocol, which stops at button 5, this includes the side buttons.
+    pub fn pressed_buttons(&self, xc: &X11Connection) -> Vec<u32> {
+        let display = xc.display;
+        let mut pressed = vec![];
+        unsafe {
+            let mut device = 0;
+            if xinput2::XIGetClientPointer(display, 0, &mut device) == 0 {
+                return pressed;
+            }
+            let root = xlib::XDefaultRootWindow(xc.display);
+            let mut root_return = 0;
+            let mut child_return = 0;
+            let mut root_x = 0.0;
+            let mut root_y = 0.0;
+            let mut win_x = 0.0;
+            let mut win_y = 0.0;
+            let mut buttons: xinput2::XIButtonState = mem::zeroed();
+            let mut modifiers: xinput2::XIModifierState = mem::zeroed();
+            let mut group: xinput2::XIModifierState = mem::zeroed();
+            if xinput2::XIQueryPointer(
+                display,
+                device,
+                root,
+                &mut root_return,
+                &mut child_return,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut buttons,
+                &mut modifiers,
+                &mut group,
+            ) == 0
+            {
+                return pressed;
+            }
+            if !buttons.mask.is_null() {
+                let mask = slice::from_raw_parts(buttons.mask, buttons.mask_len as usize);
+                for button in 1..mask.len() as u32 * 8 {
+                    if mask[button as usize / 8] & (1 << (button % 8)) != 0 {
+                        pressed.push(button);
+                    }
+                }
+                xlib::XFree(buttons.mask as *mut _);
+            }
+        }
+        pressed
+    }
+}
+
+/// A smooth scrolling valuator of a device.
+#[derive(Debug, Clone, Copy)]
+struct ScrollValuator {
+    number: i32,
+    vertical: bool,
+    /// The valuator movement of a wheel notch, negative when the axis is inverted.
+    increment: f64,
+}
+
+#[derive(Debug, Clone)]
+struct SlaveDevice {
+    valuators: Vec<ScrollValuator>,
+    source: ScrollSource,
+    /// Whether the X and Y valuators report coordinates rather than motion, as for
+    /// tablets, touchscreens and the tablet pointers of virtual machines.
+    absolute: bool,
+}
+
+/// A dedicated X connection subscribed to the raw events of the master devices.
+pub(crate) struct RawEventListener {
+    xc: X11Connection,
+    opcode: i32,
+    clock: ServerClock,
+    /// The fractional motion not reported yet, as devices can move by less than a unit.
+    motion_remainder: (f64, f64),
+    /// The valuators of the slave devices seen so far.
+    slave_devices: HashMap<i32, SlaveDevice>,
+}
+
+impl RawEventListener {
+    /// Connects to the named display, or `$DISPLAY` when `None`, and selects the raw
+    /// events on the root window.
+    pub fn open(display_name: Option<&str>) -> Result<RawEventListener, Error> {
+        let xc = X11Connection::open(display_name)?;
+        let opcode = match *xc.xinput() {
+            Ok(ref xinput) => {
+                xinput.select_raw_events(&xc);
+                xinput.opcode
+            }
+            Err(ref error) => return Err(error.clone()),
+        };
+        Ok(RawEventListener {
+            xc,
+            opcode,
+            clock: ServerClock::default(),
+            motion_remainder: (0.0, 0.0),
+            slave_devices: HashMap::new(),
+        })
+    }
+
+    /// Waits up to `timeout` for events and returns all the pending ones, along with the
+    /// time they happened.
+    pub fn next_events(&mut self, timeout: Duration) -> Result<Vec<(RawEvent, Instant)>, Error> {
+        let mut events = vec![];
+        unsafe {
+            if xlib::XQLength(self.xc.display) == 0 {
+                let mut fd = libc::pollfd {
+                    fd: xlib::XConnectionNumber(self.xc.display),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int);
+                // Detect it here, as Xlib would exit the process on a broken connection.
+                if fd.revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+                    return Err(Error::ConnectionLost);
+                }
+            }
+            let received = Instant::now();
+            while xlib::XPending(self.xc.display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                xlib::XNextEvent(self.xc.display, &mut event);
+                let cookie = &mut event.generic_event_cookie;
+                if cookie.type_ != xlib::GenericEvent
+                    || cookie.extension != self.opcode
+                    || xlib::XGetEventData(self.xc.display, cookie) == 0
+                {
+                    continue;
+                }
+                if cookie.evtype == xinput2::XI_HierarchyChanged {
+                    self.slave_devices.clear();
+                } else {
+                    let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+                    let time = self.clock.instant(raw.time, received);
+                    for event in self.translate(cookie.evtype, raw) {
+                        events.push((event, time));
+                    }
+                }
+                xlib::XFreeEventData(self.xc.display, cookie);
+            }
+        }
+        Ok(events)
+    }
+
+    fn translate(&mut self, evtype: i32, raw: &xinput2::XIRawEvent) -> Vec<RawEvent> {
+        // x11 keycode uses kernel keycode with an offset of 8.
+        let keycode = || {
+            let kernel_key = (raw.detail as u16).checked_sub(8)?;
+            Some(kernel_key_to_keycode(kernel_key))
+        };
+        match evtype {
+            xinput2::XI_RawKeyPress => keycode().map(RawEvent::KeyDown).into_iter().collect(),
+            xinput2::XI_RawKeyRelease => keycode().map(RawEvent::KeyUp).into_iter().collect(),
+            xinput2::XI_RawButtonPress => {
+                let button = raw.detail as u32;
+                let mut events = vec![RawEvent::ButtonDown(button)];
+                // Wheels without smooth scrolling valuators only click buttons 4 to 7,
+                // the others also click them but flagged as emulated.
+                if raw.flags & xinput2::XIPointerEmulated == 0 {
+                    let notch = HiResScroll::NOTCH;
+                    let (vertical, horizontal) = match button {
+                        4 => (notch, 0),
+                        5 => (-notch, 0),
+                        6 => (0, -notch),
+                        7 => (0, notch),
+                        _ => (0, 0),
+                    };
+                    if vertical != 0 || horizontal != 0 {
+                        events.push(RawEvent::Scroll(HiResScroll {
+                            vertical,
+                            horizontal,
+                            source: ScrollSource::Wheel,
+                        }));
+                    }
+                }
+                events
+            }
+            xinput2::XI_RawButtonRelease => vec![RawEvent::ButtonUp(raw.detail as u32)],
+            xinput2::XI_RawMotion => {
+                let Some(device) = self.slave_device(raw.sourceid).cloned() else {
+                    return vec![RawEvent::Moved];
+                };
+                let mut events = vec![];
+                // The first two valuators of absolute devices are coordinates in their
+                // own units, which can't be added to the motion of the mice.
+                if device.absolute {
+                    events.push(RawEvent::Moved);
+                } else {
+                    let (dx, dy) = unsafe { Self::raw_motion(raw) };
+                    let (x, y) = &mut self.motion_remainder;
+                    *x += dx;
+                    *y += dy;
+                    let delta = (x.trunc() as i32, y.trunc() as i32);
+                    *x = x.fract();
+                    *y = y.fract();
+                    events.push(RawEvent::Motion(delta));
+                }
+                events.extend(Self::raw_scroll(&device, raw).map(RawEvent::Scroll));
+                events
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Returns the valuators of a slave device, queried the first time it is seen.
+    fn slave_device(&mut self, deviceid: i32) -> Option<&SlaveDevice> {
+        let xc = &self.xc;
+        match self.slave_devices.entry(deviceid) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => match *xc.xinput() {
+                Ok(ref xinput) => Some(entry.insert(xinput.slave_device(xc, deviceid))),
+                Err(_) => None,
+            },
+        }
+    }
+
+    /// Converts the movement of the smooth scrolling valuators, if any, to 1/120 of a notch.
+    fn raw_scroll(device: &SlaveDevice, raw: &xinput2::XIRawEvent) -> Option<HiResScroll> {
+        if device.valuators.is_empty() {
+            return None;
+        }
+        let values = unsafe { Self::raw_values(raw) };
+        let mut scroll = HiResScroll {
+            source: device.source,
+            ..HiResScroll::default()
+        };
+        for valuator in &device.valuators {
+            let Some(&(_, value)) = values.iter().find(|(number, _)| *number == valuator.number)
+            else {
+                continue;
+            };
+            let units = (value / valuator.increment * HiResScroll::NOTCH as f64).round() as i32;
+            // The vertical valuator grows downwards.
+            if valuator.vertical {
+                scroll.vertical -= units;
+            } else {
+                scroll.horizontal += units;
+            }
+        }
+        (!scroll.is_zero()).then_some(scroll)
+    }
+
+    /// Returns the raw values of the valuators set in the event, with their number.
+    unsafe fn raw_values(raw: &xinput2::XIRawEvent) -> Vec<(i32, f64)> {
+        let valuators = &raw.valuators;
+        let mut values = vec![];
+        if valuators.mask.is_null() || valuators.mask_len <= 0 || raw.raw_values.is_null() {
+            return values;
+        }
+        let mask = slice::from_raw_parts(valuators.mask, valuators.mask_len as usize);
+        let mut value = raw.raw_values;
+        for number in 0..valuators.mask_len * 8 {
+            if xinput2::XIMaskIsSet(mask, number) {
+                values.push((number, *value));
+                value = value.add(1);
+            }
+        }
+        values
+    }
+
+    /// Returns the raw values of the first two valuators, the relative motion along the
+    /// X and Y axes for a mouse.
+    unsafe fn raw_motion(raw: &xinput2::XIRawEvent) -> (f64, f64) {
+        let mut motion = (0.0, 0.0);
+        for (number, value) in Self::raw_values(raw) {
+            match number {
+                0 => motion.0 = value,
+                1 => motion.1 = value,
+                _ => {}
+            }
+        }
+        motion
+    }
+
+    /// Returns the character typed by the key with the modifiers currently held.
+    pub fn key_char(&self, keycode: Keycode) -> Option<char> {
+        DeviceState::x11_char(&self.xc, keycode_to_kernel_key(keycode)?)
+    }
+
+    /// Returns the lock keys toggled on.
+    pub fn lock_modifiers(&self) -> Modifiers {
+        DeviceState::x11_lock_modifiers(&self.xc)
+    }
+
+    /// Query the current pointer position.
+    pub fn pointer_position(&self) -> MousePosition {
+        DeviceState::x11_pointer(&self.xc).0
+    }
+}
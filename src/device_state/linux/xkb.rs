@@ -0,0 +1,90 @@
+//! Translation of keys into the characters they type in the current keyboard layout.
+
+extern crate xkbcommon_dl;
+
+use self::xkbcommon_dl::{
+    xkb_context, xkb_context_flags, xkb_key_direction, xkb_keymap, xkb_keymap_compile_flags,
+    xkbcommon_option,
+};
+use std::ptr;
+
+/// Returns the character typed by a keysym, if it is a printable one.
+pub(super) fn keysym_to_char(keysym: u32) -> Option<char> {
+    let code = match xkbcommon_option() {
+        Some(xkb) => unsafe { (xkb.xkb_keysym_to_utf32)(keysym) },
+        // Without libxkbcommon, only Latin-1 and Unicode keysyms can be translated.
+        None => match keysym {
+            0x20..=0x7e | 0xa0..=0xff => keysym,
+            0x0100_0000..=0x0110_ffff => keysym - 0x0100_0000,
+            _ => 0,
+        },
+    };
+    char::from_u32(code).filter(|c| !c.is_control())
+}
+
+/// A keymap compiled by libxkbcommon, for the evdev backend which has no display
+/// server to ask for the keyboard layout.
+#[derive(Debug)]
+pub(super) struct XkbKeymap {
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+}
+
+// The keymap is never modified once compiled, every lookup uses its own state.
+unsafe impl Send for XkbKeymap {}
+unsafe impl Sync for XkbKeymap {}
+
+impl XkbKeymap {
+    /// Compiles the layout given by the `XKB_DEFAULT_*` environment variables, or the
+    /// system default one. Returns `None` if libxkbcommon is not installed.
+    pub fn new() -> Option<XkbKeymap> {
+        let xkb = xkbcommon_option()?;
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = (xkb.xkb_keymap_new_from_names)(
+                context,
+                ptr::null(),
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            if keymap.is_null() {
+                (xkb.xkb_context_unref)(context);
+                return None;
+            }
+            Some(XkbKeymap { context, keymap })
+        }
+    }
+
+    /// Returns the character typed by the kernel keycode `code` while the `pressed`
+    /// keys are held down.
+    pub fn key_char(&self, code: u16, pressed: &[u16]) -> Option<char> {
+        let xkb = xkbcommon_option()?;
+        // XKB keycodes are kernel keycodes with an offset of 8, as in X11.
+        let keysym = unsafe {
+            let state = (xkb.xkb_state_new)(self.keymap);
+            if state.is_null() {
+                return None;
+            }
+            for key in pressed.iter().filter(|key| **key != code) {
+                (xkb.xkb_state_update_key)(state, *key as u32 + 8, xkb_key_direction::XKB_KEY_DOWN);
+            }
+            let keysym = (xkb.xkb_state_key_get_one_sym)(state, code as u32 + 8);
+            (xkb.xkb_state_unref)(state);
+            keysym
+        };
+        keysym_to_char(keysym)
+    }
+}
+
+impl Drop for XkbKeymap {
+    fn drop(&mut self) {
+        if let Some(xkb) = xkbcommon_option() {
+            unsafe {
+                (xkb.xkb_keymap_unref)(self.keymap);
+                (xkb.xkb_context_unref)(self.context);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Monitor geometry with the XRandR extension.
+
+use super::x11::{xlib, xrandr};
+use super::X11Connection;
+use error::Error;
+use monitor::Monitor;
+use std::ffi::CStr;
+use std::slice;
+
+/// Access to the functions of `libXrandr`, once the server was checked to support monitors
+/// (RandR 1.5).
+#[derive(Debug)]
+pub(super) struct XRandR {
+    _supported: (),
+}
+
+impl XRandR {
+    pub fn open(xc: &X11Connection) -> Result<XRandR, Error> {
+        let mut event = 0;
+        let mut error = 0;
+        let mut major = 0;
+        let mut minor = 0;
+        let supported = unsafe {
+            xrandr::XRRQueryExtension(xc.display, &mut event, &mut error) != 0
+                && xrandr::XRRQueryVersion(xc.display, &mut major, &mut minor) != 0
+        };
+        if !supported || (major, minor) < (1, 5) {
+            return Err(Error::BackendUnavailable(
+                "XRandR 1.5 is not supported".to_string(),
+            ));
+        }
+        Ok(XRandR { _supported: () })
+    }
+
+    /// Returns the active monitors of the default screen, all with the same scale factor
+    /// since X11 has no per-monitor one.
+    pub fn monitors(&self, xc: &X11Connection, scale_factor: f64) -> Vec<Monitor> {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(xc.display);
+            let mut count = 0;
+            let infos = xrandr::XRRGetMonitors(xc.display, root, xlib::True, &mut count);
+            if infos.is_null() {
+                return vec![];
+            }
+            let monitors = slice::from_raw_parts(infos, count.max(0) as usize)
+                .iter()
+                .map(|info| Monitor {
+                    name: atom_name(xc, info.name),
+                    position: (info.x, info.y),
+                    size: (info.width.max(0) as u32, info.height.max(0) as u32),
+                    scale_factor,
+                    primary: info.primary != 0,
+                })
+                .collect();
+            xrandr::XRRFreeMonitors(infos);
+            monitors
+        }
+    }
+}
+
+fn atom_name(xc: &X11Connection, atom: xlib::Atom) -> String {
+    // Asking the name of `None` would be a protocol error.
+    if atom == 0 {
+        return String::new();
+    }
+    unsafe {
+        let name = xlib::XGetAtomName(xc.display, atom);
+        if name.is_null() {
+            return String::new();
+        }
+        let string = CStr::from_ptr(name).to_string_lossy().into_owned();
+        xlib::XFree(name as *mut _);
+        string
+    }
+}
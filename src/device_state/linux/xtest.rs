@@ -0,0 +1,91 @@
+//! Input synthesis with the XTest extension.
+
+use super::x11::{xlib, xtest};
+use super::X11Connection;
+use error::Error;
+use mouse_state::{MousePosition, ScrollDelta};
+use std::os::raw::{c_int, c_uint, c_ulong};
+
+/// `CurrentTime`, to process the events right away.
+const CURRENT_TIME: c_ulong = 0;
+
+/// Access to the functions of `libXtst`, once the server was checked to support them.
+#[derive(Debug)]
+pub(super) struct XTest {
+    _supported: (),
+}
+
+impl XTest {
+    pub fn open(xc: &X11Connection) -> Result<XTest, Error> {
+        let mut event = 0;
+        let mut error = 0;
+        let mut major = 0;
+        let mut minor = 0;
+        let supported = unsafe {
+            xtest::XTestQueryExtension(xc.display, &mut event, &mut error, &mut major, &mut minor)
+        };
+        if supported == 0 {
+            return Err(Error::BackendUnavailable(
+                "XTest is not supported".to_string(),
+            ));
+        }
+        Ok(XTest { _supported: () })
+    }
+
+    /// Presses or releases the key with the given X11 keycode.
+    pub fn key(&self, xc: &X11Connection, keycode: u8, pressed: bool) {
+        unsafe {
+            xtest::XTestFakeKeyEvent(
+                xc.display,
+                keycode as c_uint,
+                pressed as c_int,
+                CURRENT_TIME,
+            );
+        }
+        Self::sync(xc);
+    }
+
+    pub fn motion(&self, xc: &X11Connection, (x, y): MousePosition) {
+        unsafe {
+            // -1 is the screen the pointer is on.
+            xtest::XTestFakeMotionEvent(xc.display, -1, x, y, CURRENT_TIME);
+        }
+        Self::sync(xc);
+    }
+
+    /// Presses or releases the given X11 button.
+    pub fn button(&self, xc: &X11Connection, button: u8, pressed: bool) {
+        unsafe {
+            xtest::XTestFakeButtonEvent(
+                xc.display,
+                button as c_uint,
+                pressed as c_int,
+                CURRENT_TIME,
+            );
+        }
+        Self::sync(xc);
+    }
+
+    /// Clicks the wheel buttons once per notch.
+    pub fn scroll(&self, xc: &X11Connection, delta: ScrollDelta) {
+        // In X11, buttons 4/5 are scroll up/down, buttons 6/7 are scroll left/right.
+        let vertical = if delta.vertical > 0 { 4 } else { 5 };
+        let horizontal = if delta.horizontal > 0 { 7 } else { 6 };
+        for (button, notches) in [
+            (vertical, delta.vertical.unsigned_abs()),
+            (horizontal, delta.horizontal.unsigned_abs()),
+        ] {
+            for _ in 0..notches {
+                self.button(xc, button, true);
+                self.button(xc, button, false);
+            }
+        }
+    }
+
+    /// Waits for the server to process the events, so that they can be queried back.
+    fn sync(xc: &X11Connection) {
+        unsafe {
+            xlib::XSync(xc.display, xlib::False);
+        }
+    }
+}
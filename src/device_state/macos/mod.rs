@@ -1,10 +1,15 @@
 extern crate macos_accessibility_client;
 
+use error::Error;
 use keymap::Keycode;
-use mouse_state::{MouseState, ScrollDelta};
+use modifiers::Modifiers;
+use monitor::Monitor;
+use mouse_state::{HiResScroll, MouseButton, MousePosition, MouseState, ScrollDelta, ScrollSource};
 use std::ffi::c_void;
 use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, Ordering};
+use std::sync::Mutex;
 use std::thread;
+use std::time::{Duration, Instant};
 
 #[allow(dead_code, non_camel_case_types)]
 mod cg_ffi {
@@ -17,6 +22,29 @@ mod cg_ffi {
     pub type CFRunLoopRef = *mut c_void;
     pub type CFAllocatorRef = *const c_void;
     pub type CFStringRef = *const c_void;
+    pub type CGDirectDisplayID = u32;
+    pub type CGDisplayModeRef = *mut c_void;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct CGPoint {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct CGSize {
+        pub width: f64,
+        pub height: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct CGRect {
+        pub origin: CGPoint,
+        pub size: CGSize,
+    }
 
     pub const K_CG_HID_EVENT_TAP: u32 = 0;
     pub const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
@@ -25,6 +53,9 @@ mod cg_ffi {
     pub const K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFFFFFE;
     pub const K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS1: u32 = 11; // vertical
     pub const K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS2: u32 = 12; // horizontal
+    pub const K_CG_SCROLL_WHEEL_EVENT_IS_CONTINUOUS: u32 = 88;
+    pub const K_CG_SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS1: u32 = 93; // vertical, in lines
+    pub const K_CG_SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS2: u32 = 94; // horizontal, in lines
 
     pub type CGEventTapCallBack = unsafe extern "C" fn(
         proxy: CGEventTapProxy,
@@ -45,6 +76,18 @@ mod cg_ffi {
         ) -> CFMachPortRef;
         pub fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
         pub fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+        pub fn CGEventGetDoubleValueField(event: CGEventRef, field: u32) -> f64;
+        pub fn CGGetActiveDisplayList(
+            max_displays: u32,
+            active_displays: *mut CGDirectDisplayID,
+            display_count: *mut u32,
+        ) -> i32;
+        pub fn CGMainDisplayID() -> CGDirectDisplayID;
+        pub fn CGDisplayBounds(display: CGDirectDisplayID) -> CGRect;
+        pub fn CGDisplayCopyDisplayMode(display: CGDirectDisplayID) -> CGDisplayModeRef;
+        pub fn CGDisplayModeGetWidth(mode: CGDisplayModeRef) -> usize;
+        pub fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+        pub fn CGDisplayModeRelease(mode: CGDisplayModeRef);
     }
 
     #[link(name = "CoreFoundation", kind = "framework")]
@@ -69,9 +112,16 @@ use self::cg_ffi::*;
 // Global scroll accumulators
 static SCROLL_VERTICAL: AtomicI32 = AtomicI32::new(0);
 static SCROLL_HORIZONTAL: AtomicI32 = AtomicI32::new(0);
+static HI_RES_VERTICAL: AtomicI32 = AtomicI32::new(0);
+static HI_RES_HORIZONTAL: AtomicI32 = AtomicI32::new(0);
+static HI_RES_CONTINUOUS: AtomicBool = AtomicBool::new(false);
 static HOOK_INITIALIZED: AtomicBool = AtomicBool::new(false);
 static EVENT_TAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
 
+/// How long the monitors located by `query_pointer` are reused before being queried again.
+const MONITORS_REFRESH: Duration = Duration::from_secs(1);
+static MONITORS: Mutex<Option<(Instant, Vec<Monitor>)>> = Mutex::new(None);
+
 unsafe extern "C" fn scroll_callback(
     _proxy: CGEventTapProxy,
     event_type: u32,
@@ -93,6 +143,23 @@ unsafe extern "C" fn scroll_callback(
             CGEventGetIntegerValueField(event, K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS2) as i32;
         SCROLL_VERTICAL.fetch_add(vertical, Ordering::Relaxed);
         SCROLL_HORIZONTAL.fetch_add(horizontal, Ordering::Relaxed);
+
+        // The fixed point deltas keep the fractions of lines, converted to 1/120 of a notch.
+        let hi_res = |field| {
+            (CGEventGetDoubleValueField(event, field) * HiResScroll::NOTCH as f64).round() as i32
+        };
+        HI_RES_VERTICAL.fetch_add(
+            hi_res(K_CG_SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS1),
+            Ordering::Relaxed,
+        );
+        HI_RES_HORIZONTAL.fetch_add(
+            hi_res(K_CG_SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS2),
+            Ordering::Relaxed,
+        );
+        HI_RES_CONTINUOUS.store(
+            CGEventGetIntegerValueField(event, K_CG_SCROLL_WHEEL_EVENT_IS_CONTINUOUS) != 0,
+            Ordering::Relaxed,
+        );
     }
     event
 }
@@ -259,21 +326,28 @@ impl DeviceState {
 
     /// returns `None` if app doesn't accessibility permissions.
     pub fn checked_new() -> Option<DeviceState> {
+        Self::try_new().ok()
+    }
+
+    /// Fails with [`Error::PermissionDenied`] if app doesn't have accessibility permissions.
+    pub fn try_new() -> Result<DeviceState, Error> {
         if has_accessibility() {
             init_scroll_hook();
-            Some(DeviceState {})
+            Ok(DeviceState {})
         } else {
-            None
+            Err(Error::PermissionDenied)
         }
     }
 
     pub fn query_pointer(&self) -> MouseState {
         let (x, y) = readmouse::Mouse::location();
+        // Same order as `MouseButton::number`, the side buttons can't be read.
         let button_pressed = vec![
             false,
             readmouse::Mouse::Left.is_pressed(),
-            readmouse::Mouse::Right.is_pressed(),
             readmouse::Mouse::Center.is_pressed(),
+            readmouse::Mouse::Right.is_pressed(),
+            false,
             false,
         ];
 
@@ -282,14 +356,137 @@ impl DeviceState {
             vertical: SCROLL_VERTICAL.swap(0, Ordering::Relaxed),
             horizontal: SCROLL_HORIZONTAL.swap(0, Ordering::Relaxed),
         };
+        let hi_res_scroll = HiResScroll {
+            vertical: HI_RES_VERTICAL.swap(0, Ordering::Relaxed),
+            horizontal: HI_RES_HORIZONTAL.swap(0, Ordering::Relaxed),
+            source: if HI_RES_CONTINUOUS.load(Ordering::Relaxed) {
+                ScrollSource::Continuous
+            } else {
+                ScrollSource::Wheel
+            },
+        };
 
-        MouseState {
-            coords: (x as i32, y as i32),
+        let coords = (x as i32, y as i32);
+        let mut mouse_state = MouseState {
+            coords,
             button_pressed,
             scroll_delta,
+            hi_res_scroll,
+            motion_delta: (0, 0),
+            monitor: None,
+            monitor_coords: coords,
+        };
+        self.locate_monitor(&mut mouse_state);
+        mouse_state
+    }
+
+    /// Locates the pointer on the displays, listed again when the last listing is older
+    /// than `MONITORS_REFRESH` so that polling stays cheap.
+    fn locate_monitor(&self, mouse_state: &mut MouseState) {
+        let mut monitors = match MONITORS.lock() {
+            Ok(monitors) => monitors,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let fresh = matches!(*monitors, Some((queried, _)) if queried.elapsed() < MONITORS_REFRESH);
+        if !fresh {
+            *monitors = Some((Instant::now(), Self::active_displays()));
+        }
+        if let Some((_, ref monitors)) = *monitors {
+            mouse_state.locate_monitor(monitors);
         }
     }
 
+    /// Query the active displays, in points like the pointer coordinates. Their names are
+    /// the display IDs, the localized names being only available through AppKit.
+    pub fn query_monitors(&self) -> Vec<Monitor> {
+        let monitors = Self::active_displays();
+        let mut cache = match MONITORS.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *cache = Some((Instant::now(), monitors.clone()));
+        monitors
+    }
+
+    fn active_displays() -> Vec<Monitor> {
+        let mut displays = [0; 32];
+        let mut count = 0;
+        unsafe {
+            if CGGetActiveDisplayList(displays.len() as u32, displays.as_mut_ptr(), &mut count) != 0
+            {
+                return vec![];
+            }
+            let main = CGMainDisplayID();
+            displays[..count as usize]
+                .iter()
+                .map(|&display| {
+                    let bounds = CGDisplayBounds(display);
+                    let mode = CGDisplayCopyDisplayMode(display);
+                    let scale_factor = if mode.is_null() {
+                        1.0
+                    } else {
+                        let width = CGDisplayModeGetWidth(mode);
+                        let pixel_width = CGDisplayModeGetPixelWidth(mode);
+                        CGDisplayModeRelease(mode);
+                        if width > 0 {
+                            pixel_width as f64 / width as f64
+                        } else {
+                            1.0
+                        }
+                    };
+                    Monitor {
+                        name: display.to_string(),
+                        position: (bounds.origin.x as i32, bounds.origin.y as i32),
+                        size: (bounds.size.width as u32, bounds.size.height as u32),
+                        scale_factor,
+                        primary: display == main,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// Query the modifiers held. Lock states are not available on macOS.
+    pub fn query_modifiers(&self) -> Modifiers {
+        Modifiers::from_keys(&self.query_keymap())
+    }
+
+    /// Layout translation is not available on macOS, always returns `None`.
+    pub fn query_char(&self, _keycode: Keycode) -> Option<char> {
+        None
+    }
+
+    /// Key names are not available on macOS, always returns `None`.
+    pub fn raw_key_name(&self, _code: u16) -> Option<String> {
+        None
+    }
+
+    /// Synthesizing input is not supported on macOS yet.
+    pub fn send_key(&self, _key: Keycode, _pressed: bool) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on macOS yet.
+    pub fn send_mouse_move(&self, _position: MousePosition) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on macOS yet.
+    pub fn send_mouse_button(&self, _button: MouseButton, _pressed: bool) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on macOS yet.
+    pub fn send_scroll(&self, _delta: ScrollDelta) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    fn control_unavailable() -> Result<(), Error> {
+        Err(Error::BackendUnavailable(
+            "synthesizing input is not supported on macOS".to_string(),
+        ))
+    }
+
     pub fn query_keymap(&self) -> Vec<Keycode> {
         MAPPING
             .iter()
@@ -297,6 +494,24 @@ impl DeviceState {
             .map(|(_, to)| *to)
             .collect()
     }
+
+    /// Same as [`query_pointer`](Self::query_pointer), failing with
+    /// [`Error::PermissionDenied`] once the Accessibility permissions are revoked.
+    pub fn try_query_pointer(&self) -> Result<MouseState, Error> {
+        if !is_trusted() {
+            return Err(Error::PermissionDenied);
+        }
+        Ok(self.query_pointer())
+    }
+
+    /// Same as [`query_keymap`](Self::query_keymap), failing with
+    /// [`Error::PermissionDenied`] once the Accessibility permissions are revoked.
+    pub fn try_query_keymap(&self) -> Result<Vec<Keycode>, Error> {
+        if !is_trusted() {
+            return Err(Error::PermissionDenied);
+        }
+        Ok(self.query_keymap())
+    }
 }
 
 /// Returns true if the Accessibility permissions necessary for this library to work are granted
@@ -316,3 +531,8 @@ fn has_accessibility() -> bool {
     // With prompting:
     application_is_trusted_with_prompt()
 }
+
+/// Same as `has_accessibility`, without prompting the user again.
+fn is_trusted() -> bool {
+    self::macos_accessibility_client::accessibility::application_is_trusted()
+}
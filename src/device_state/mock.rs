@@ -0,0 +1,337 @@
+//! Fake device state for tests.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::sleep;
+use std::time::Duration;
+use {DeviceControl, DeviceQuery, Error, Keycode, MouseButton, MousePosition, MouseState};
+use {HiResScroll, Modifiers, Monitor, MouseDelta, ScrollDelta, ScrollSource};
+
+#[derive(Debug, Default)]
+struct MockState {
+    keys: Vec<Keycode>,
+    locks: Modifiers,
+    mouse: MouseState,
+    monitors: Vec<Monitor>,
+    error: Option<Error>,
+}
+
+/// A [`DeviceQuery`] whose state is set by the program instead of read from the devices,
+/// to test the code using it without a display or any hardware.
+///
+/// Clones share the same state, so one of them can be given to the code under test, or to
+/// [`DeviceEventsHandler::with_device_query`](crate::DeviceEventsHandler::with_device_query),
+/// while the test drives another one. The state is changed with the [`DeviceControl`] functions,
+/// the functions of this type, or a script, see [`run_script`](Self::run_script).
+///
+/// ```
+/// use device_query::{DeviceControl, DeviceQuery, Keycode, MockDeviceState};
+///
+/// let device_state = MockDeviceState::new();
+/// device_state.key_down(Keycode::LControl).unwrap();
+/// device_state.mouse_move_to((10, 20)).unwrap();
+///
+/// assert_eq!(device_state.get_keys(), vec![Keycode::LControl]);
+/// assert!(device_state.get_modifiers().control());
+/// assert_eq!(device_state.get_mouse().coords, (10, 20));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockDeviceState {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockDeviceState {
+    /// Creates a device state with no key or button pressed, and the mouse at `(0, 0)`.
+    pub fn new() -> MockDeviceState {
+        MockDeviceState::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // The state stays consistent even if a test panicked while holding it.
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Sets all the pressed keys at once.
+    pub fn set_keys(&self, keys: &[Keycode]) {
+        self.state().keys = keys.to_vec();
+    }
+
+    /// Sets the lock keys toggled on, returned by [`DeviceQuery::get_modifiers`] along
+    /// with the modifiers held.
+    pub fn set_lock_modifiers(&self, locks: Modifiers) {
+        self.state().locks =
+            locks & (Modifiers::CAPS_LOCK | Modifiers::NUM_LOCK | Modifiers::SCROLL_LOCK);
+    }
+
+    /// Sets the monitors returned by [`DeviceQuery::get_monitors`], which [`MouseState::monitor`]
+    /// is found in. There are none by default.
+    ///
+    /// ```
+    /// use device_query::{DeviceControl, DeviceQuery, MockDeviceState, Monitor};
+    ///
+    /// let monitor = |name: &str, x, primary| Monitor {
+    ///     name: name.to_string(),
+    ///     position: (x, 0),
+    ///     size: (1920, 1080),
+    ///     scale_factor: 1.0,
+    ///     primary,
+    /// };
+    /// let device_state = MockDeviceState::new();
+    /// device_state.set_monitors(&[monitor("left", 0, true), monitor("right", 1920, false)]);
+    /// device_state.mouse_move_to((2000, 300)).unwrap();
+    ///
+    /// let mouse = device_state.get_mouse();
+    /// assert_eq!(mouse.monitor, Some(1));
+    /// assert_eq!(mouse.monitor_coords, (80, 300));
+    /// ```
+    pub fn set_monitors(&self, monitors: &[Monitor]) {
+        self.state().monitors = monitors.to_vec();
+    }
+
+    /// Makes [`DeviceQuery::try_get_mouse`] and [`DeviceQuery::try_get_keys`] fail with
+    /// the error until it is cleared with `None`, as if the devices couldn't be read.
+    ///
+    /// ```
+    /// use device_query::{DeviceEvents, DeviceEventsHandler, Error, MockDeviceState};
+    /// use std::sync::mpsc::channel;
+    /// use std::time::Duration;
+    ///
+    /// let device_state = MockDeviceState::new();
+    /// let device_events =
+    ///     DeviceEventsHandler::with_device_query(device_state.clone(), Duration::from_millis(1));
+    /// let (sender, receiver) = channel();
+    /// let _guard = device_events.on_error(move |error| sender.send(error.clone()).unwrap());
+    ///
+    /// device_state.set_error(Some(Error::ConnectionLost));
+    /// assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(Error::ConnectionLost));
+    /// ```
+    pub fn set_error(&self, error: Option<Error>) {
+        self.state().error = error;
+    }
+
+    /// Runs a script changing the state, one action per line:
+    ///
+    /// * `press <key>` and `release <key>`, with a [`Keycode`] name,
+    /// * `move <x> <y>`, and `motion <dx> <dy>` for the relative motion,
+    /// * `down <button>` and `up <button>`, with a [`MouseButton::number`],
+    /// * `scroll <vertical> <horizontal>`,
+    /// * `wait <milliseconds>`, to let an event loop polling this state see the changes.
+    ///
+    /// Empty lines and lines starting with `#` are ignored. The whole script is parsed
+    /// before running, so nothing is done if it has an error.
+    ///
+    /// ```
+    /// use device_query::{DeviceQuery, Keycode, MockDeviceState, MouseButton};
+    ///
+    /// let device_state = MockDeviceState::new();
+    /// device_state
+    ///     .run_script(
+    ///         "press LShift
+    ///          press A
+    ///          release A
+    ///          move 100 50
+    ///          down 1",
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(device_state.get_keys(), vec![Keycode::LShift]);
+    /// assert_eq!(device_state.get_mouse().coords, (100, 50));
+    /// assert!(device_state.get_mouse().is_pressed(MouseButton::Left));
+    /// assert!(device_state.run_script("press Nope").is_err());
+    /// ```
+    pub fn run_script(&self, script: &str) -> Result<(), String> {
+        let actions = script
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(index, line)| {
+                Action::parse(line).map_err(|error| format!("line {}: {}", index + 1, error))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for action in actions {
+            match action {
+                Action::Press(key) => self.press_key(key),
+                Action::Release(key) => self.release_key(key),
+                Action::Move(position) => self.move_mouse(position),
+                Action::Motion(delta) => self.add_motion(delta),
+                Action::Down(button) => self.set_button(button, true),
+                Action::Up(button) => self.set_button(button, false),
+                Action::Scroll(delta) => self.add_scroll(delta),
+                Action::Wait(duration) => sleep(duration),
+            }
+        }
+        Ok(())
+    }
+
+    fn press_key(&self, key: Keycode) {
+        let mut state = self.state();
+        if !state.keys.contains(&key) {
+            state.keys.push(key);
+        }
+    }
+
+    fn release_key(&self, key: Keycode) {
+        self.state().keys.retain(|pressed| *pressed != key);
+    }
+
+    fn move_mouse(&self, position: MousePosition) {
+        self.state().mouse.coords = position;
+    }
+
+    fn add_motion(&self, (dx, dy): MouseDelta) {
+        let mut state = self.state();
+        state.mouse.motion_delta.0 += dx;
+        state.mouse.motion_delta.1 += dy;
+    }
+
+    fn set_button(&self, button: MouseButton, pressed: bool) {
+        self.state().mouse.set_pressed(button, pressed);
+    }
+
+    fn add_scroll(&self, delta: ScrollDelta) {
+        let mut state = self.state();
+        state.mouse.scroll_delta.vertical += delta.vertical;
+        state.mouse.scroll_delta.horizontal += delta.horizontal;
+        state.mouse.hi_res_scroll.vertical += delta.vertical * HiResScroll::NOTCH;
+        state.mouse.hi_res_scroll.horizontal += delta.horizontal * HiResScroll::NOTCH;
+        state.mouse.hi_res_scroll.source = ScrollSource::Wheel;
+    }
+}
+
+impl DeviceQuery for MockDeviceState {
+    /// Returns the mouse state, and the motion and scroll deltas accumulated since the last call.
+    fn get_mouse(&self) -> MouseState {
+        let mut state = self.state();
+        let mut mouse = state.mouse.clone();
+        state.mouse.scroll_delta = ScrollDelta::default();
+        state.mouse.hi_res_scroll = HiResScroll::default();
+        state.mouse.motion_delta = (0, 0);
+        // Same 1-based layout as the platforms, with at least five buttons.
+        if mouse.button_pressed.len() < 6 {
+            mouse.button_pressed.resize(6, false);
+        }
+        mouse.locate_monitor(&state.monitors);
+        mouse
+    }
+
+    fn get_keys(&self) -> Vec<Keycode> {
+        self.state().keys.clone()
+    }
+
+    fn try_get_mouse(&self) -> Result<MouseState, Error> {
+        let error = self.state().error.clone();
+        match error {
+            Some(error) => Err(error),
+            None => Ok(self.get_mouse()),
+        }
+    }
+
+    fn try_get_keys(&self) -> Result<Vec<Keycode>, Error> {
+        let error = self.state().error.clone();
+        match error {
+            Some(error) => Err(error),
+            None => Ok(self.get_keys()),
+        }
+    }
+
+    fn get_modifiers(&self) -> Modifiers {
+        let state = self.state();
+        Modifiers::from_keys(&state.keys) | state.locks
+    }
+
+    fn get_monitors(&self) -> Vec<Monitor> {
+        self.state().monitors.clone()
+    }
+}
+
+impl DeviceControl for MockDeviceState {
+    fn key_down(&self, key: Keycode) -> Result<(), Error> {
+        self.press_key(key);
+        Ok(())
+    }
+
+    fn key_up(&self, key: Keycode) -> Result<(), Error> {
+        self.release_key(key);
+        Ok(())
+    }
+
+    fn mouse_move_to(&self, position: MousePosition) -> Result<(), Error> {
+        self.move_mouse(position);
+        Ok(())
+    }
+
+    fn mouse_button(&self, button: MouseButton, pressed: bool) -> Result<(), Error> {
+        self.set_button(button, pressed);
+        Ok(())
+    }
+
+    /// Adds to the scroll delta returned by the next [`DeviceQuery::get_mouse`].
+    fn scroll(&self, delta: ScrollDelta) -> Result<(), Error> {
+        self.add_scroll(delta);
+        Ok(())
+    }
+}
+
+/// An action of a [`MockDeviceState`] script.
+enum Action {
+    Press(Keycode),
+    Release(Keycode),
+    Move(MousePosition),
+    Motion(MouseDelta),
+    Down(MouseButton),
+    Up(MouseButton),
+    Scroll(ScrollDelta),
+    Wait(Duration),
+}
+
+impl Action {
+    fn parse(line: &str) -> Result<Action, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let arguments: Vec<&str> = words.collect();
+        let number = |index: usize| -> Result<i64, String> {
+            let argument = arguments
+                .get(index)
+                .ok_or_else(|| format!("missing argument for {:?}", command))?;
+            argument
+                .parse()
+                .map_err(|_| format!("invalid number {:?}", argument))
+        };
+        let key = || -> Result<Keycode, String> {
+            Keycode::from_str(arguments.first().copied().unwrap_or_default())
+        };
+        let button = || -> Result<MouseButton, String> {
+            usize::try_from(number(0)?)
+                .ok()
+                .and_then(MouseButton::from_number)
+                .ok_or_else(|| "invalid button".to_string())
+        };
+        let expected = match command {
+            "press" | "release" | "down" | "up" | "wait" => 1,
+            "move" | "motion" | "scroll" => 2,
+            _ => return Err(format!("unknown action {:?}", command)),
+        };
+        if arguments.len() != expected {
+            return Err(format!("{:?} takes {} arguments", command, expected));
+        }
+        Ok(match command {
+            "press" => Action::Press(key()?),
+            "release" => Action::Release(key()?),
+            "move" => Action::Move((number(0)? as i32, number(1)? as i32)),
+            "motion" => Action::Motion((number(0)? as i32, number(1)? as i32)),
+            "down" => Action::Down(button()?),
+            "up" => Action::Up(button()?),
+            "scroll" => Action::Scroll(ScrollDelta {
+                vertical: number(0)? as i32,
+                horizontal: number(1)? as i32,
+            }),
+            _ => Action::Wait(Duration::from_millis(
+                u64::try_from(number(0)?).map_err(|_| "invalid duration".to_string())?,
+            )),
+        })
+    }
+}
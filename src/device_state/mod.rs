@@ -17,10 +17,17 @@
 //! 
 //! ```
 
+mod mock;
+pub use self::mock::MockDeviceState;
+
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
-pub use self::linux::DeviceState;
+pub use self::linux::{Backend, DeviceState, VirtualDevice};
+#[cfg(target_os = "linux")]
+pub(crate) use self::linux::xinput2::{RawEvent, RawEventListener};
+#[cfg(target_os = "linux")]
+pub(crate) use self::linux::mouse_button_from_x11;
 
 #[cfg(target_os = "windows")]
 mod windows;
@@ -32,6 +39,15 @@ mod macos;
 #[cfg(target_os = "macos")]
 pub use self::macos::DeviceState;
 
+// `DeviceState` can be shared between threads on every platform.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    #[allow(dead_code)]
+    fn assert_device_state() {
+        assert_send_sync::<DeviceState>();
+    }
+};
+
 impl Default for DeviceState {
     fn default() -> Self {
         Self::new()
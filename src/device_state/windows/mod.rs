@@ -1,20 +1,40 @@
+use error::Error;
 use keymap::Keycode;
-use mouse_state::{MouseState, ScrollDelta};
+use modifiers::Modifiers;
+use monitor::Monitor;
+use mouse_state::{HiResScroll, MouseButton, MousePosition, MouseState, ScrollDelta, ScrollSource};
+use std::mem;
 use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Mutex;
 use std::thread;
-use windows::Win32::Foundation::{LPARAM, LRESULT, POINT, WPARAM};
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{BOOL, LPARAM, LRESULT, POINT, RECT, TRUE, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
 use windows::Win32::UI::Input::KeyboardAndMouse;
-use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VIRTUAL_KEY};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, GetKeyNameTextW, GetKeyState, GetKeyboardLayout, MapVirtualKeyW,
+    ToUnicodeEx, MAPVK_VK_TO_VSC, VIRTUAL_KEY,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
-    CallNextHookEx, DispatchMessageW, GetCursorPos, GetMessageW, SetWindowsHookExW, MSG,
-    MSLLHOOKSTRUCT, WH_MOUSE_LL, WM_MOUSEWHEEL, WM_MOUSEHWHEEL,
+    CallNextHookEx, DispatchMessageW, GetCursorPos, GetForegroundWindow, GetMessageW,
+    GetWindowThreadProcessId, SetWindowsHookExW, MONITORINFOF_PRIMARY, MSG, MSLLHOOKSTRUCT,
+    WH_MOUSE_LL, WM_MOUSEWHEEL, WM_MOUSEHWHEEL,
 };
 
 // Global scroll accumulator
 static SCROLL_VERTICAL: AtomicI32 = AtomicI32::new(0);
 static SCROLL_HORIZONTAL: AtomicI32 = AtomicI32::new(0);
+static HI_RES_VERTICAL: AtomicI32 = AtomicI32::new(0);
+static HI_RES_HORIZONTAL: AtomicI32 = AtomicI32::new(0);
 static HOOK_INITIALIZED: AtomicBool = AtomicBool::new(false);
 
+/// How long the monitors located by `query_pointer` are reused before being queried again.
+const MONITORS_REFRESH: Duration = Duration::from_secs(1);
+static MONITORS: Mutex<Option<(Instant, Vec<Monitor>)>> = Mutex::new(None);
+
 fn init_mouse_hook() {
     if HOOK_INITIALIZED.swap(true, Ordering::Relaxed) {
         return; // Already initialized
@@ -40,13 +60,16 @@ unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPA
     if code >= 0 {
         let hook_struct = &*(lparam.0 as *const MSLLHOOKSTRUCT);
         match wparam.0 as u32 {
+            // The wheel delta is already in 1/120 of a notch.
             WM_MOUSEWHEEL => {
-                let delta = ((hook_struct.mouseData >> 16) as i16) / 120;
-                SCROLL_VERTICAL.fetch_add(delta as i32, Ordering::Relaxed);
+                let hi_res = (hook_struct.mouseData >> 16) as i16;
+                HI_RES_VERTICAL.fetch_add(hi_res as i32, Ordering::Relaxed);
+                SCROLL_VERTICAL.fetch_add((hi_res / 120) as i32, Ordering::Relaxed);
             }
             WM_MOUSEHWHEEL => {
-                let delta = ((hook_struct.mouseData >> 16) as i16) / 120;
-                SCROLL_HORIZONTAL.fetch_add(delta as i32, Ordering::Relaxed);
+                let hi_res = (hook_struct.mouseData >> 16) as i16;
+                HI_RES_HORIZONTAL.fetch_add(hi_res as i32, Ordering::Relaxed);
+                SCROLL_HORIZONTAL.fetch_add((hi_res / 120) as i32, Ordering::Relaxed);
             }
             _ => {}
         }
@@ -54,6 +77,43 @@ unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPA
     CallNextHookEx(None, code, wparam, lparam)
 }
 
+// Called by `EnumDisplayMonitors` for each monitor, with the `Vec` to add it to.
+unsafe extern "system" fn monitor_enum_proc(
+    monitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+    if GetMonitorInfoW(monitor, &mut info.monitorInfo as *mut MONITORINFO).as_bool() {
+        let rect = info.monitorInfo.rcMonitor;
+        let (mut dpi_x, mut dpi_y) = (0, 0);
+        let scale_factor =
+            match GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) {
+                Ok(()) if dpi_x > 0 => dpi_x as f64 / 96.0,
+                _ => 1.0,
+            };
+        let name_len = info
+            .szDevice
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(info.szDevice.len());
+        monitors.push(Monitor {
+            name: String::from_utf16_lossy(&info.szDevice[..name_len]),
+            position: (rect.left, rect.top),
+            size: (
+                (rect.right - rect.left).max(0) as u32,
+                (rect.bottom - rect.top).max(0) as u32,
+            ),
+            scale_factor,
+            primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+        });
+    }
+    TRUE
+}
+
 #[derive(Debug, Clone)]
 pub struct DeviceState;
 
@@ -68,6 +128,11 @@ impl DeviceState {
         Some(Self::new())
     }
 
+    // Adding because Linux and OSX supports this where `new` can fail.
+    pub fn try_new() -> Result<Self, Error> {
+        Ok(Self::new())
+    }
+
     pub fn query_pointer(&self) -> MouseState {
         let point = &mut POINT { x: 0, y: 0 };
         let button1pressed;
@@ -84,10 +149,11 @@ impl DeviceState {
             };
             button1pressed =
                 GetAsyncKeyState(KeyboardAndMouse::VK_LBUTTON.0 as i32) as u32 & 0x8000 != 0;
+            // Same order as `MouseButton::number`: left, middle, right, back, forward.
             button2pressed =
-                GetAsyncKeyState(KeyboardAndMouse::VK_RBUTTON.0 as i32) as u32 & 0x8000 != 0;
-            button3pressed =
                 GetAsyncKeyState(KeyboardAndMouse::VK_MBUTTON.0 as i32) as u32 & 0x8000 != 0;
+            button3pressed =
+                GetAsyncKeyState(KeyboardAndMouse::VK_RBUTTON.0 as i32) as u32 & 0x8000 != 0;
             button4pressed =
                 GetAsyncKeyState(KeyboardAndMouse::VK_XBUTTON1.0 as i32) as u32 & 0x8000 != 0;
             button5pressed =
@@ -98,8 +164,14 @@ impl DeviceState {
             vertical: SCROLL_VERTICAL.swap(0, Ordering::Relaxed),
             horizontal: SCROLL_HORIZONTAL.swap(0, Ordering::Relaxed),
         };
+        // Touchpads send the same messages as wheels.
+        let hi_res_scroll = HiResScroll {
+            vertical: HI_RES_VERTICAL.swap(0, Ordering::Relaxed),
+            horizontal: HI_RES_HORIZONTAL.swap(0, Ordering::Relaxed),
+            source: ScrollSource::Wheel,
+        };
 
-        MouseState {
+        let mut mouse_state = MouseState {
             coords,
             button_pressed: vec![
                 false,
@@ -110,9 +182,72 @@ impl DeviceState {
                 button5pressed,
             ],
             scroll_delta,
+            hi_res_scroll,
+            motion_delta: (0, 0),
+            monitor: None,
+            monitor_coords: coords,
+        };
+        self.locate_monitor(&mut mouse_state);
+        mouse_state
+    }
+
+    /// Locates the pointer on the monitors, enumerated again when the last enumeration
+    /// is older than `MONITORS_REFRESH` so that polling stays cheap.
+    fn locate_monitor(&self, mouse_state: &mut MouseState) {
+        let mut monitors = match MONITORS.lock() {
+            Ok(monitors) => monitors,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let fresh = matches!(*monitors, Some((queried, _)) if queried.elapsed() < MONITORS_REFRESH);
+        if !fresh {
+            *monitors = Some((Instant::now(), Self::enum_monitors()));
+        }
+        if let Some((_, ref monitors)) = *monitors {
+            mouse_state.locate_monitor(monitors);
         }
     }
 
+    /// Query the monitors, with their effective DPI as scale factor.
+    pub fn query_monitors(&self) -> Vec<Monitor> {
+        let monitors = Self::enum_monitors();
+        let mut cache = match MONITORS.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *cache = Some((Instant::now(), monitors.clone()));
+        monitors
+    }
+
+    fn enum_monitors() -> Vec<Monitor> {
+        let mut monitors: Vec<Monitor> = vec![];
+        unsafe {
+            EnumDisplayMonitors(
+                None,
+                None,
+                Some(monitor_enum_proc),
+                LPARAM(&mut monitors as *mut Vec<Monitor> as isize),
+            );
+        }
+        monitors
+    }
+
+    /// Same as [`query_pointer`](Self::query_pointer), failing with
+    /// [`Error::PermissionDenied`] when the cursor can't be read, as while the secure
+    /// desktop of the lock screen or of a UAC prompt is shown.
+    pub fn try_query_pointer(&self) -> Result<MouseState, Error> {
+        let mut point = POINT { x: 0, y: 0 };
+        let readable: bool = unsafe { GetCursorPos(&mut point) }.into();
+        if !readable {
+            return Err(Error::PermissionDenied);
+        }
+        Ok(self.query_pointer())
+    }
+
+    /// Same as [`query_keymap`](Self::query_keymap), which can't fail on Windows.
+    pub fn try_query_keymap(&self) -> Result<Vec<Keycode>, Error> {
+        Ok(self.query_keymap())
+    }
+
     pub fn query_keymap(&self) -> Vec<Keycode> {
         let mut keycodes = vec![];
         let mut keymap = vec![];
@@ -204,6 +339,42 @@ impl DeviceState {
             KeyboardAndMouse::VK_OEM_COMMA => Some(Keycode::Comma),
             KeyboardAndMouse::VK_OEM_PERIOD => Some(Keycode::Dot),
             KeyboardAndMouse::VK_OEM_2 => Some(Keycode::Slash),
+            KeyboardAndMouse::VK_F21 => Some(Keycode::F21),
+            KeyboardAndMouse::VK_F22 => Some(Keycode::F22),
+            KeyboardAndMouse::VK_F23 => Some(Keycode::F23),
+            KeyboardAndMouse::VK_F24 => Some(Keycode::F24),
+            KeyboardAndMouse::VK_SNAPSHOT => Some(Keycode::PrintScreen),
+            KeyboardAndMouse::VK_SCROLL => Some(Keycode::ScrollLock),
+            KeyboardAndMouse::VK_PAUSE => Some(Keycode::Pause),
+            KeyboardAndMouse::VK_APPS => Some(Keycode::Menu),
+            KeyboardAndMouse::VK_NUMLOCK => Some(Keycode::NumLock),
+            KeyboardAndMouse::VK_SEPARATOR => Some(Keycode::NumpadComma),
+            KeyboardAndMouse::VK_OEM_102 => Some(Keycode::IntlBackslash),
+            KeyboardAndMouse::VK_CONVERT => Some(Keycode::Henkan),
+            KeyboardAndMouse::VK_NONCONVERT => Some(Keycode::Muhenkan),
+            KeyboardAndMouse::VK_HANGUL => Some(Keycode::Hangeul),
+            KeyboardAndMouse::VK_HANJA => Some(Keycode::Hanja),
+            KeyboardAndMouse::VK_VOLUME_MUTE => Some(Keycode::Mute),
+            KeyboardAndMouse::VK_VOLUME_DOWN => Some(Keycode::VolumeDown),
+            KeyboardAndMouse::VK_VOLUME_UP => Some(Keycode::VolumeUp),
+            KeyboardAndMouse::VK_MEDIA_PLAY_PAUSE => Some(Keycode::MediaPlayPause),
+            KeyboardAndMouse::VK_PLAY => Some(Keycode::MediaPlay),
+            KeyboardAndMouse::VK_MEDIA_STOP => Some(Keycode::MediaStop),
+            KeyboardAndMouse::VK_MEDIA_PREV_TRACK => Some(Keycode::MediaPrevious),
+            KeyboardAndMouse::VK_MEDIA_NEXT_TRACK => Some(Keycode::MediaNext),
+            KeyboardAndMouse::VK_BROWSER_BACK => Some(Keycode::BrowserBack),
+            KeyboardAndMouse::VK_BROWSER_FORWARD => Some(Keycode::BrowserForward),
+            KeyboardAndMouse::VK_BROWSER_REFRESH => Some(Keycode::BrowserRefresh),
+            KeyboardAndMouse::VK_BROWSER_STOP => Some(Keycode::BrowserStop),
+            KeyboardAndMouse::VK_BROWSER_SEARCH => Some(Keycode::BrowserSearch),
+            KeyboardAndMouse::VK_BROWSER_FAVORITES => Some(Keycode::BrowserFavorites),
+            KeyboardAndMouse::VK_BROWSER_HOME => Some(Keycode::BrowserHome),
+            KeyboardAndMouse::VK_LAUNCH_MAIL => Some(Keycode::LaunchMail),
+            KeyboardAndMouse::VK_LAUNCH_MEDIA_SELECT => Some(Keycode::LaunchMediaPlayer),
+            KeyboardAndMouse::VK_LAUNCH_APP1 => Some(Keycode::LaunchMyComputer),
+            KeyboardAndMouse::VK_LAUNCH_APP2 => Some(Keycode::LaunchCalculator),
+            KeyboardAndMouse::VK_SLEEP => Some(Keycode::Sleep),
+            KeyboardAndMouse::VK_HELP => Some(Keycode::Help),
 
             _ => None,
         };
@@ -250,6 +421,116 @@ impl DeviceState {
                 _ => None,
             }
         }
-        keycode
+        match VIRTUAL_KEY(win_key) {
+            // Mouse buttons are part of the `MouseState`, and the modifiers without a side
+            // are already reported through their left or right variant.
+            KeyboardAndMouse::VK_LBUTTON
+            | KeyboardAndMouse::VK_RBUTTON
+            | KeyboardAndMouse::VK_CANCEL
+            | KeyboardAndMouse::VK_MBUTTON
+            | KeyboardAndMouse::VK_XBUTTON1
+            | KeyboardAndMouse::VK_XBUTTON2
+            | KeyboardAndMouse::VK_SHIFT
+            | KeyboardAndMouse::VK_CONTROL
+            | KeyboardAndMouse::VK_MENU => None,
+            _ => Some(keycode.unwrap_or(Keycode::Unknown(win_key))),
+        }
+    }
+
+    /// Query the modifiers held and the lock keys toggled on.
+    pub fn query_modifiers(&self) -> Modifiers {
+        let mut modifiers = Modifiers::from_keys(&self.query_keymap());
+        unsafe {
+            let toggled = |key: VIRTUAL_KEY| GetKeyState(key.0 as i32) & 1 != 0;
+            modifiers.set(Modifiers::CAPS_LOCK, toggled(KeyboardAndMouse::VK_CAPITAL));
+            modifiers.set(Modifiers::NUM_LOCK, toggled(KeyboardAndMouse::VK_NUMLOCK));
+            modifiers.set(Modifiers::SCROLL_LOCK, toggled(KeyboardAndMouse::VK_SCROLL));
+        }
+        modifiers
+    }
+
+    /// Returns the character typed by the key in the keyboard layout of the foreground
+    /// window with the modifiers currently held.
+    pub fn query_char(&self, keycode: Keycode) -> Option<char> {
+        let win_key = (0..256).find(|key| self.win_key_to_keycode(*key) == Some(keycode))?;
+        let mut key_state = [0u8; 256];
+        let mut buffer = [0u16; 8];
+        let length = unsafe {
+            for (key, state) in key_state.iter_mut().enumerate() {
+                if GetAsyncKeyState(key as i32) as u32 & 0x8000 != 0 {
+                    *state = 0x80;
+                }
+            }
+            for lock in [
+                KeyboardAndMouse::VK_CAPITAL,
+                KeyboardAndMouse::VK_NUMLOCK,
+                KeyboardAndMouse::VK_SCROLL,
+            ] {
+                if GetKeyState(lock.0 as i32) & 1 != 0 {
+                    key_state[lock.0 as usize] |= 1;
+                }
+            }
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+            let scan_code = MapVirtualKeyW(win_key as u32, MAPVK_VK_TO_VSC);
+            // Flag 0x4 leaves the dead key state of the keyboard untouched.
+            ToUnicodeEx(
+                win_key as u32,
+                scan_code,
+                &key_state,
+                &mut buffer,
+                0x4,
+                GetKeyboardLayout(thread),
+            )
+        };
+        if length <= 0 {
+            return None;
+        }
+        char::decode_utf16(buffer[..length as usize].iter().copied())
+            .next()?
+            .ok()
+            .filter(|c| !c.is_control())
+    }
+
+    /// Returns the localized name of a raw virtual-key code, such as the one carried by
+    /// [`Keycode::Unknown`].
+    pub fn raw_key_name(&self, code: u16) -> Option<String> {
+        let mut name = [0u16; 64];
+        let length = unsafe {
+            let scan_code = MapVirtualKeyW(code as u32, MAPVK_VK_TO_VSC);
+            if scan_code == 0 {
+                return None;
+            }
+            GetKeyNameTextW((scan_code << 16) as i32, &mut name)
+        };
+        if length <= 0 {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&name[..length as usize]))
+    }
+
+    /// Synthesizing input is not supported on Windows yet.
+    pub fn send_key(&self, _key: Keycode, _pressed: bool) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on Windows yet.
+    pub fn send_mouse_move(&self, _position: MousePosition) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on Windows yet.
+    pub fn send_mouse_button(&self, _button: MouseButton, _pressed: bool) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    /// Synthesizing input is not supported on Windows yet.
+    pub fn send_scroll(&self, _delta: ScrollDelta) -> Result<(), Error> {
+        Self::control_unavailable()
+    }
+
+    fn control_unavailable() -> Result<(), Error> {
+        Err(Error::BackendUnavailable(
+            "synthesizing input is not supported on Windows".to_string(),
+        ))
     }
 }
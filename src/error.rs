@@ -0,0 +1,36 @@
+//! Errors.
+
+use std::error;
+use std::fmt;
+
+/// Errors returned when the devices can't be queried or controlled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Could not connect to the X display.
+    NoDisplay,
+    /// The process is not allowed to read the devices: missing Accessibility permissions
+    /// on macOS, or no read access to `/dev/input` on Linux.
+    PermissionDenied,
+    /// The backend can't be used on this system, for instance because a library,
+    /// an extension or the devices themselves are missing.
+    BackendUnavailable(String),
+    /// The connection to the devices was lost while reading from them.
+    ConnectionLost,
+    /// The input event can't be synthesized, for instance a key the backend has no code for.
+    UnsupportedInput(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoDisplay => write!(f, "Could not connect to a X display"),
+            Error::PermissionDenied => write!(f, "Not allowed to read the input devices"),
+            Error::BackendUnavailable(reason) => write!(f, "Backend unavailable: {}", reason),
+            Error::ConnectionLost => write!(f, "Lost the connection to the input devices"),
+            Error::UnsupportedInput(input) => write!(f, "Unsupported input: {}", input),
+        }
+    }
+}
+
+impl error::Error for Error {}
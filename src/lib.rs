@@ -34,14 +34,27 @@
 #[cfg(target_os = "windows")]
 extern crate windows;
 
+#[macro_use]
+extern crate bitflags;
+#[cfg(feature = "async")]
+extern crate futures_core;
+
+pub mod device_control;
 pub mod device_events;
 pub mod device_query;
 pub mod device_state;
+pub mod error;
 pub mod keymap;
+pub mod modifiers;
+pub mod monitor;
 pub mod mouse_state;
 
+pub use device_control::*;
 pub use device_events::*;
 pub use device_query::*;
 pub use device_state::*;
+pub use error::*;
 pub use keymap::*;
+pub use modifiers::*;
+pub use monitor::*;
 pub use mouse_state::*;
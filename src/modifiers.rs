@@ -0,0 +1,87 @@
+//! State of the modifier and lock keys.
+
+use keymap::Keycode;
+
+bitflags! {
+    /// The modifier keys held down and the lock keys toggled on.
+    ///
+    /// Each side of a modifier has its own flag, while `SHIFT`, `CONTROL`, `ALT` and
+    /// `META` hold both sides:
+    ///
+    /// ```
+    /// use device_query::{Keycode, Modifiers};
+    ///
+    /// let modifiers = Modifiers::from_keys(&[Keycode::RControl, Keycode::A]);
+    /// assert!(modifiers.control());
+    /// assert!(modifiers.contains(Modifiers::RCONTROL));
+    /// assert!(!modifiers.contains(Modifiers::CONTROL));
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Modifiers: u16 {
+        const LSHIFT = 1 << 0;
+        const RSHIFT = 1 << 1;
+        const LCONTROL = 1 << 2;
+        const RCONTROL = 1 << 3;
+        /// Left Alt, or left Option on macOS.
+        const LALT = 1 << 4;
+        /// Right Alt (AltGr on some layouts), or right Option on macOS.
+        const RALT = 1 << 5;
+        /// Left Windows/Super key, or left Command on macOS.
+        const LMETA = 1 << 6;
+        /// Right Windows/Super key, or right Command on macOS.
+        const RMETA = 1 << 7;
+        const CAPS_LOCK = 1 << 8;
+        const NUM_LOCK = 1 << 9;
+        const SCROLL_LOCK = 1 << 10;
+
+        const SHIFT = Self::LSHIFT.bits() | Self::RSHIFT.bits();
+        const CONTROL = Self::LCONTROL.bits() | Self::RCONTROL.bits();
+        const ALT = Self::LALT.bits() | Self::RALT.bits();
+        const META = Self::LMETA.bits() | Self::RMETA.bits();
+    }
+}
+
+impl Modifiers {
+    /// Returns the modifier flag of a key, or no flag if it isn't a modifier key.
+    pub fn from_key(key: Keycode) -> Modifiers {
+        match key {
+            Keycode::LShift => Modifiers::LSHIFT,
+            Keycode::RShift => Modifiers::RSHIFT,
+            Keycode::LControl => Modifiers::LCONTROL,
+            Keycode::RControl => Modifiers::RCONTROL,
+            Keycode::LAlt | Keycode::LOption => Modifiers::LALT,
+            Keycode::RAlt | Keycode::ROption => Modifiers::RALT,
+            Keycode::LMeta | Keycode::Command => Modifiers::LMETA,
+            Keycode::RMeta | Keycode::RCommand => Modifiers::RMETA,
+            _ => Modifiers::empty(),
+        }
+    }
+
+    /// Returns the modifiers held among the given pressed keys. The lock states can't
+    /// be told from the keys, so they are never set.
+    pub fn from_keys(keys: &[Keycode]) -> Modifiers {
+        keys.iter().fold(Modifiers::empty(), |modifiers, key| {
+            modifiers | Modifiers::from_key(*key)
+        })
+    }
+
+    /// Returns true if either shift key is held.
+    pub fn shift(&self) -> bool {
+        self.intersects(Modifiers::SHIFT)
+    }
+
+    /// Returns true if either control key is held.
+    pub fn control(&self) -> bool {
+        self.intersects(Modifiers::CONTROL)
+    }
+
+    /// Returns true if either alt key is held.
+    pub fn alt(&self) -> bool {
+        self.intersects(Modifiers::ALT)
+    }
+
+    /// Returns true if either meta key is held.
+    pub fn meta(&self) -> bool {
+        self.intersects(Modifiers::META)
+    }
+}
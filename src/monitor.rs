@@ -0,0 +1,47 @@
+//! Description of the monitors the pointer moves across.
+
+use mouse_state::MousePosition;
+
+/// A monitor, as a rectangle of the desktop the pointer coordinates are relative to.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Monitor {
+    /// Name given by the platform, such as the output name (`"HDMI-1"`) on Linux.
+    pub name: String,
+    /// Coordinates of the top-left corner, in pixel.
+    pub position: MousePosition,
+    /// Width and height, in pixel.
+    pub size: (u32, u32),
+    /// Number of physical pixels per logical pixel, such as `2.0` on a HiDPI display.
+    pub scale_factor: f64,
+    /// Whether this is the primary monitor.
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// Returns true if the pointer coordinates are on this monitor.
+    pub fn contains(&self, (x, y): MousePosition) -> bool {
+        let (left, top) = self.position;
+        let (width, height) = self.size;
+        x >= left && y >= top && x - left < width as i32 && y - top < height as i32
+    }
+
+    /// Converts pointer coordinates to coordinates relative to the top-left corner of this monitor.
+    ///
+    /// ```
+    /// use device_query::Monitor;
+    ///
+    /// let monitor = Monitor {
+    ///     name: "DP-2".to_string(),
+    ///     position: (1920, 0),
+    ///     size: (2560, 1440),
+    ///     scale_factor: 1.0,
+    ///     primary: false,
+    /// };
+    /// assert!(monitor.contains((2000, 100)));
+    /// assert!(!monitor.contains((100, 100)));
+    /// assert_eq!(monitor.to_local((2000, 100)), (80, 100));
+    /// ```
+    pub fn to_local(&self, (x, y): MousePosition) -> MousePosition {
+        (x - self.position.0, y - self.position.1)
+    }
+}
@@ -1,10 +1,94 @@
 //! Description of mouse coordinates and state of buttons.
 
+use monitor::Monitor;
+use std::convert::TryFrom;
+use std::fmt;
+
 /// Mouse position.
 pub type MousePosition = (i32, i32);
 
-/// MouseButton.
-pub type MouseButton = usize;
+/// Relative mouse motion, in device units.
+pub type MouseDelta = (i32, i32);
+
+/// A mouse button. The scroll wheel isn't one, its movements are reported as
+/// [`ScrollDelta`] and [`MouseScrollEvent`] instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MouseButton {
+    /// Left button.
+    Left,
+    /// Middle button, usually the wheel click.
+    Middle,
+    /// Right button.
+    Right,
+    /// First side button, going back in browsers.
+    Back,
+    /// Second side button, going forward in browsers.
+    Forward,
+    /// Any other button, numbered from 6 to 255.
+    Other(ButtonNumber),
+}
+
+/// The number of a button beyond the five named ones, always from 6 to 255 so that
+/// each button has a single value. It is obtained through [`MouseButton::from_number`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ButtonNumber(u8);
+
+impl ButtonNumber {
+    /// Returns the number, from 6 to 255.
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl MouseButton {
+    /// Returns the number of the button, its index in [`MouseState::button_pressed`]:
+    /// 1 to 5 for `Left`, `Middle`, `Right`, `Back` and `Forward`, and 6 to 255 for `Other`.
+    pub fn number(self) -> usize {
+        match self {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+            MouseButton::Back => 4,
+            MouseButton::Forward => 5,
+            MouseButton::Other(number) => number.get() as usize,
+        }
+    }
+
+    /// Returns the button with the given number, `None` for 0 and numbers above 255.
+    ///
+    /// ```
+    /// use device_query::MouseButton;
+    ///
+    /// assert_eq!(MouseButton::from_number(3), Some(MouseButton::Right));
+    /// assert_eq!(MouseButton::from_number(0), None);
+    /// let button = MouseButton::from_number(8).unwrap();
+    /// assert!(matches!(button, MouseButton::Other(number) if number.get() == 8));
+    /// assert_eq!(button.number(), 8);
+    /// assert_eq!(button.to_string(), "Button8");
+    /// ```
+    pub fn from_number(number: usize) -> Option<MouseButton> {
+        match number {
+            0 => None,
+            1 => Some(MouseButton::Left),
+            2 => Some(MouseButton::Middle),
+            3 => Some(MouseButton::Right),
+            4 => Some(MouseButton::Back),
+            5 => Some(MouseButton::Forward),
+            number => u8::try_from(number)
+                .ok()
+                .map(|number| MouseButton::Other(ButtonNumber(number))),
+        }
+    }
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MouseButton::Other(number) => write!(f, "Button{}", number.get()),
+            button => write!(f, "{:?}", button),
+        }
+    }
+}
 
 /// Scroll delta represents scroll wheel movement.
 /// Values can be positive or negative indicating direction.
@@ -16,6 +100,39 @@ pub struct ScrollDelta {
     pub horizontal: i32,
 }
 
+/// What produced a scroll.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum ScrollSource {
+    /// A wheel turning by notches, possibly split into finer steps by a high-resolution wheel.
+    #[default]
+    Wheel,
+    /// A continuous movement, such as scrolling with two fingers on a touchpad.
+    Continuous,
+}
+
+/// High-resolution scroll movement, in 1/120 of a wheel notch: a notch is
+/// [`HiResScroll::NOTCH`], while touchpads and high-resolution wheels move by fractions of it.
+/// Positive values have the same directions as [`ScrollDelta`].
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub struct HiResScroll {
+    /// Vertical movement (positive = up)
+    pub vertical: i32,
+    /// Horizontal movement (positive = right)
+    pub horizontal: i32,
+    /// What produced the movement. When several devices scrolled, the last one.
+    pub source: ScrollSource,
+}
+
+impl HiResScroll {
+    /// The movement of one wheel notch.
+    pub const NOTCH: i32 = 120;
+
+    /// Returns true if there was no movement.
+    pub fn is_zero(&self) -> bool {
+        self.vertical == 0 && self.horizontal == 0
+    }
+}
+
 /// Represents a mouse scroll event with direction.
 #[derive(Debug, PartialEq, Clone)]
 pub enum MouseScrollEvent {
@@ -32,9 +149,10 @@ pub enum MouseScrollEvent {
 #[derive(Debug, PartialEq, Default, Clone)]
 /// A simple structure containing the current mouse coordinates and the
 /// state of each mouse button that we can query. Currently, Windows and
-/// Linux provide nice ways to query five mouse buttons. Since button
-/// numbers are 1-based, `button_pressed[0]` is assumed to be false and
-/// have no meaning.
+/// Linux provide nice ways to query five mouse buttons, and Linux reports
+/// the extra ones as well. `button_pressed` is indexed by
+/// [`MouseButton::number`], so `button_pressed[0]` is always false and
+/// has no meaning; [`MouseState::is_pressed`] reads it by button.
 pub struct MouseState {
     /// Coordinates in pixel.
     pub coords: MousePosition,
@@ -42,4 +160,56 @@ pub struct MouseState {
     pub button_pressed: Vec<bool>,
     /// Scroll wheel delta since last query.
     pub scroll_delta: ScrollDelta,
+    /// High-resolution scroll movement since last query, keeping the fractions of
+    /// notches that `scroll_delta` drops.
+    pub hi_res_scroll: HiResScroll,
+    /// Unaccelerated relative motion since last query, which keeps being reported when
+    /// the cursor is stuck against a screen edge. Only available on Linux, zero elsewhere.
+    pub motion_delta: MouseDelta,
+    /// Index in [`DeviceQuery::get_monitors`](crate::DeviceQuery::get_monitors) of the
+    /// monitor under the cursor, `None` when the monitors are unknown.
+    pub monitor: Option<usize>,
+    /// Coordinates relative to the top-left corner of `monitor`, the same as `coords`
+    /// when it is `None`.
+    pub monitor_coords: MousePosition,
+}
+
+impl MouseState {
+    /// Returns true if the button is pressed.
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.button_pressed
+            .get(button.number())
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Returns the buttons pressed.
+    pub fn pressed_buttons(&self) -> Vec<MouseButton> {
+        self.button_pressed
+            .iter()
+            .enumerate()
+            .filter(|(_, pressed)| **pressed)
+            .filter_map(|(number, _)| MouseButton::from_number(number))
+            .collect()
+    }
+
+    /// Sets the state of a button, growing `button_pressed` as needed.
+    pub(crate) fn set_pressed(&mut self, button: MouseButton, pressed: bool) {
+        let number = button.number();
+        if self.button_pressed.len() <= number {
+            self.button_pressed.resize(number + 1, false);
+        }
+        self.button_pressed[number] = pressed;
+    }
+
+    /// Sets `monitor` and `monitor_coords` to the monitor containing `coords`.
+    pub(crate) fn locate_monitor(&mut self, monitors: &[Monitor]) {
+        self.monitor = monitors
+            .iter()
+            .position(|monitor| monitor.contains(self.coords));
+        self.monitor_coords = match self.monitor {
+            Some(index) => monitors[index].to_local(self.coords),
+            None => self.coords,
+        };
+    }
 }
@@ -0,0 +1,160 @@
+//! Synthesizes input with XTest and reads it back from the X server of `$DISPLAY`, both by
+//! querying the state and through the XInput2 events.
+//!
+//! The tests return early when `$DISPLAY` isn't set. They press keys and move the pointer
+//! of that server, so run them on a virtual one: `xvfb-run cargo test --test x11`.
+
+#![cfg(target_os = "linux")]
+
+extern crate device_query;
+
+use device_query::{DeviceControl, DeviceEvent, DeviceEventsHandler, DeviceQuery, DeviceState};
+use device_query::{Keycode, MouseButton, ScrollDelta};
+use std::env;
+use std::sync::mpsc::Receiver;
+use std::sync::{Mutex, MutexGuard};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Held by each test, as they share the keyboard and the pointer of the server.
+static SERVER: Mutex<()> = Mutex::new(());
+
+/// Returns the name of the display with the lock on its devices, `None` without display.
+fn display() -> Option<(String, MutexGuard<'static, ()>)> {
+    let display = env::var("DISPLAY").ok()?;
+    let guard = match SERVER.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    Some((display, guard))
+}
+
+/// Polls until the condition holds, the server handling the requests asynchronously.
+fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+/// Waits for an event matching the predicate, skipping the others.
+fn expect_event(events: &Receiver<DeviceEvent>, predicate: impl Fn(&DeviceEvent) -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+        match events.recv_timeout(timeout) {
+            Ok(event) if predicate(&event) => return true,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+#[test]
+fn keys_are_read_back() {
+    let Some((display, _guard)) = display() else {
+        return;
+    };
+    let device_state = DeviceState::with_display(&display);
+
+    device_state.key_down(Keycode::LShift).unwrap();
+    device_state.key_down(Keycode::A).unwrap();
+    assert!(wait_until(|| {
+        let keys = device_state.get_keys();
+        keys.contains(&Keycode::LShift) && keys.contains(&Keycode::A)
+    }));
+
+    device_state.key_up(Keycode::A).unwrap();
+    device_state.key_up(Keycode::LShift).unwrap();
+    assert!(wait_until(|| device_state.get_keys().is_empty()));
+}
+
+#[test]
+fn mouse_is_read_back() {
+    let Some((display, _guard)) = display() else {
+        return;
+    };
+    let device_state = DeviceState::with_display(&display);
+
+    device_state.mouse_move_to((100, 200)).unwrap();
+    assert!(wait_until(|| device_state.get_mouse().coords == (100, 200)));
+    device_state.mouse_move_to((120, 230)).unwrap();
+    assert!(wait_until(|| device_state.get_mouse().coords == (120, 230)));
+
+    for button in [MouseButton::Left, MouseButton::Right, MouseButton::Back] {
+        device_state.mouse_button(button, true).unwrap();
+        assert!(wait_until(|| device_state.get_mouse().is_pressed(button)));
+        device_state.mouse_button(button, false).unwrap();
+        assert!(wait_until(|| !device_state.get_mouse().is_pressed(button)));
+    }
+}
+
+#[test]
+fn scroll_is_read_back() {
+    let Some((display, _guard)) = display() else {
+        return;
+    };
+    let device_state = DeviceState::with_display(&display);
+    let clone = device_state.clone();
+
+    device_state
+        .scroll(ScrollDelta {
+            vertical: -2,
+            horizontal: 1,
+        })
+        .unwrap();
+    // Each query returns the scrolling since the previous one of the same clone.
+    for device_state in [&device_state, &clone] {
+        let mut scrolled = ScrollDelta::default();
+        assert!(wait_until(|| {
+            let delta = device_state.get_mouse().scroll_delta;
+            scrolled.vertical += delta.vertical;
+            scrolled.horizontal += delta.horizontal;
+            scrolled
+                == ScrollDelta {
+                    vertical: -2,
+                    horizontal: 1,
+                }
+        }));
+    }
+}
+
+#[test]
+fn events_are_dispatched() {
+    let Some((display, _guard)) = display() else {
+        return;
+    };
+    let device_state = DeviceState::with_display(&display);
+    // Polling this slowly would miss the taps below, which XInput2 reports anyway.
+    let device_events =
+        DeviceEventsHandler::with_display(&display, Duration::from_secs(10)).unwrap();
+    let events = device_events.subscribe();
+
+    device_state.key_down(Keycode::A).unwrap();
+    device_state.key_up(Keycode::A).unwrap();
+    assert!(expect_event(&events, |event| {
+        matches!(event, DeviceEvent::KeyDown(event) if event.key == Keycode::A)
+    }));
+    assert!(expect_event(&events, |event| {
+        matches!(event, DeviceEvent::KeyUp(event) if event.key == Keycode::A)
+    }));
+
+    device_state.mouse_button(MouseButton::Left, true).unwrap();
+    device_state.mouse_button(MouseButton::Left, false).unwrap();
+    assert!(expect_event(&events, |event| {
+        *event == DeviceEvent::MouseDown(MouseButton::Left)
+    }));
+    assert!(expect_event(&events, |event| {
+        *event == DeviceEvent::MouseUp(MouseButton::Left)
+    }));
+
+    device_state.mouse_move_to((50, 60)).unwrap();
+    device_state.mouse_move_to((80, 100)).unwrap();
+    assert!(expect_event(&events, |event| {
+        *event == DeviceEvent::MouseMove((80, 100))
+    }));
+}